@@ -0,0 +1,169 @@
+use geometry::base::{Angle, Point, Vector};
+use geometry::shape::*;
+use hexpong::component::{ball, block};
+use hexpong::gamemode;
+use hexpong::gym;
+use hexpong::levels;
+use hexpong::scene;
+use hexpong::settings;
+use hexpong::{InputState, PhysicalComponent};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+const SAMPLES: usize = 20000;
+const DENSE_LEVEL_RADIUS: i32 = 12;
+const EPISODES: u64 = 200;
+const EPISODE_TICKS: u64 = 600;
+
+fn main() {
+    run(
+        &scene::list_config_files(settings::LEVEL_DIR),
+        &scene::list_config_files(settings::GAMEMODE_DIR),
+    );
+}
+
+fn run(level_paths: &[String], gamemode_paths: &[String]) {
+    println!(
+        "Block collision queries, {} random ball sweeps per level",
+        SAMPLES
+    );
+    for level_path in level_paths.iter() {
+        match levels::Level::new(level_path.clone()) {
+            Ok(level) => compare(&level),
+            Err(error) => println!("{}", error),
+        }
+    }
+    compare(&dense_level(DENSE_LEVEL_RADIUS));
+
+    println!(
+        "Headless environment, {} episodes of up to {} ticks with random actions",
+        EPISODES, EPISODE_TICKS
+    );
+    for gamemode_path in gamemode_paths.iter() {
+        match gym::Environment::new(
+            gamemode_path,
+            gamemode::Difficulty::Normal,
+            0,
+            EPISODE_TICKS,
+        ) {
+            Ok(mut environment) => episodes(gamemode_path, &mut environment),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+fn episodes(gamemode_path: &str, environment: &mut gym::Environment) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut ticks = 0;
+    let mut total_reward = 0.0;
+    let start = Instant::now();
+    for episode in 0..EPISODES {
+        environment.reset(episode);
+        loop {
+            let actions: Vec<InputState> = (0..environment.player_count())
+                .map(|_| InputState {
+                    left: rng.gen_range(0.0, 1.0),
+                    right: rng.gen_range(0.0, 1.0),
+                    launch: true,
+                    target: None,
+                })
+                .collect();
+            let (_, rewards, done) = environment.step(&actions);
+            total_reward += rewards.iter().sum::<f32>();
+            ticks += 1;
+            if done {
+                break;
+            }
+        }
+    }
+    let time = start.elapsed();
+    println!(
+        "{:<40} {:>8.0} episodes/s  {:>10.0} ticks/s  mean reward {:>7.1}",
+        gamemode_path,
+        EPISODES as f64 / time.as_secs_f64(),
+        ticks as f64 / time.as_secs_f64(),
+        total_reward / EPISODES as f32
+    );
+}
+
+fn dense_level(radius: i32) -> levels::Level {
+    let block_size = 0.6 * settings::UNIT_SIZE / (1.5 * radius as f32 + 1.0);
+    let blocks = block::GridIndex { q: 0, r: 0 }
+        .range(radius)
+        .into_iter()
+        .map(|index| block::Block::new(index, block_size, block::BlockType::Basic))
+        .collect();
+    levels::Level::from_blocks(
+        String::new(),
+        format!("Dense (radius {})", radius),
+        levels::LevelShape::Hexagonal,
+        block_size,
+        levels::Explosions::default(),
+        levels::Multiball::default(),
+        blocks,
+    )
+}
+
+fn compare(level: &levels::Level) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let samples: Vec<(ball::Ball, Vector)> = (0..SAMPLES)
+        .map(|_| {
+            let mut ball = ball::Ball::new(
+                Point::new(settings::BALL_SPAWN.0, settings::BALL_SPAWN.1),
+                0.9,
+                &mut rng,
+            );
+            ball.shape.move_to(Point::new(
+                rng.gen_range(-0.7, 0.7) * settings::UNIT_SIZE,
+                rng.gen_range(-0.7, 0.7) * settings::UNIT_SIZE,
+            ));
+            let motion = Vector::from_magnitude(
+                settings::norm_to_unit(0.9) * settings::TICK_DURATION,
+                Angle::new(rng.gen_range(0.0, 360.0)),
+            );
+            (ball, motion)
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut linear_hits = 0;
+    for (ball, motion) in samples.iter() {
+        for block in level.blocks.iter() {
+            if block.collision(ball, *motion).is_some() {
+                linear_hits += 1;
+            }
+        }
+    }
+    let linear_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut grid_hits = 0;
+    for (ball, motion) in samples.iter() {
+        let reach = motion.magnitude() / 2.0 + ball.shape.radius();
+        let (x, y) = (
+            ball.shape.center().x + motion.dx / 2.0,
+            ball.shape.center().y + motion.dy / 2.0,
+        );
+        for block_index in level.blocks_near(x, y, reach) {
+            if level.blocks[block_index].collision(ball, *motion).is_some() {
+                grid_hits += 1;
+            }
+        }
+    }
+    let grid_time = start.elapsed();
+
+    println!(
+        "{:<24} {:>5} blocks  linear {:>10.2?}  grid {:>10.2?}  speedup {:>6.1}x{}",
+        level.name,
+        level.blocks.len(),
+        linear_time,
+        grid_time,
+        linear_time.as_secs_f64() / grid_time.as_secs_f64(),
+        if linear_hits == grid_hits {
+            String::new()
+        } else {
+            format!("  MISMATCH {} / {} hits", linear_hits, grid_hits)
+        }
+    );
+}
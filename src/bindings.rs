@@ -0,0 +1,434 @@
+use crate::config;
+use crate::config::ConfigError;
+use crate::settings;
+use ggez::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+const KEYS: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("0", KeyCode::Key0),
+    ("Numpad1", KeyCode::Numpad1),
+    ("Numpad2", KeyCode::Numpad2),
+    ("Numpad3", KeyCode::Numpad3),
+    ("Numpad4", KeyCode::Numpad4),
+    ("Numpad5", KeyCode::Numpad5),
+    ("Numpad6", KeyCode::Numpad6),
+    ("Numpad7", KeyCode::Numpad7),
+    ("Numpad8", KeyCode::Numpad8),
+    ("Numpad9", KeyCode::Numpad9),
+    ("Numpad0", KeyCode::Numpad0),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Space", KeyCode::Space),
+    ("Return", KeyCode::Return),
+    ("Escape", KeyCode::Escape),
+    ("Tab", KeyCode::Tab),
+    ("Back", KeyCode::Back),
+    ("LShift", KeyCode::LShift),
+    ("RShift", KeyCode::RShift),
+    ("LControl", KeyCode::LControl),
+    ("RControl", KeyCode::RControl),
+    ("LAlt", KeyCode::LAlt),
+    ("RAlt", KeyCode::RAlt),
+    ("Comma", KeyCode::Comma),
+    ("Period", KeyCode::Period),
+    ("Slash", KeyCode::Slash),
+    ("Semicolon", KeyCode::Semicolon),
+    ("Apostrophe", KeyCode::Apostrophe),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+];
+
+pub fn key_name(keycode: KeyCode) -> Option<&'static str> {
+    KEYS.iter()
+        .find(|(_, key)| *key == keycode)
+        .map(|(name, _)| *name)
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Key(pub KeyCode);
+
+impl Key {
+    pub fn from_str(input: &str) -> Option<Key> {
+        KEYS.iter()
+            .find(|(name, _)| name.to_lowercase() == input.to_lowercase())
+            .map(|(_, key)| Key(*key))
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Key::from_str(&input).ok_or_else(|| format!("Unknown key: {}", input))
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        String::from(key_name(key.0).unwrap_or("Unknown"))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Left,
+    Right,
+    Launch,
+    Pause,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Binding {
+    // pause is shared by all players
+    pub player: Option<usize>,
+    pub action: Action,
+}
+
+impl Binding {
+    pub fn key_path(&self) -> String {
+        let action = match self.action {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Launch => "launch",
+            Action::Pause => "pause",
+        };
+        match self.player {
+            Some(player) => format!("bindings.players[{}].{}", player, action),
+            None => format!("bindings.{}", action),
+        }
+    }
+    pub fn label(&self) -> String {
+        let action = match self.action {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Launch => "launch",
+            Action::Pause => "Pause",
+        };
+        match self.player {
+            Some(player) => format!("P{} {}", player + 1, action),
+            None => String::from(action),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct PlayerBindings {
+    pub left: Key,
+    pub right: Key,
+    pub launch: Key,
+}
+
+#[derive(Deserialize)]
+struct BindingsFile {
+    bindings: Bindings,
+}
+
+#[derive(Serialize)]
+struct BindingsFileRef<'a> {
+    bindings: &'a Bindings,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Bindings {
+    pub pause: Key,
+    pub players: Vec<PlayerBindings>,
+    // index of the player following the mouse, like the players list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mouse_player: Option<usize>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            pause: Key(KeyCode::Escape),
+            players: vec![
+                PlayerBindings {
+                    left: Key(KeyCode::Left),
+                    right: Key(KeyCode::Right),
+                    launch: Key(KeyCode::Up),
+                },
+                PlayerBindings {
+                    left: Key(KeyCode::A),
+                    right: Key(KeyCode::D),
+                    launch: Key(KeyCode::W),
+                },
+                PlayerBindings {
+                    left: Key(KeyCode::J),
+                    right: Key(KeyCode::L),
+                    launch: Key(KeyCode::I),
+                },
+            ],
+            mouse_player: None,
+        }
+    }
+}
+
+impl Bindings {
+    // the bindings saved by the user, or the shipped defaults until then
+    pub fn current_file() -> &'static str {
+        if std::path::Path::new(settings::USER_BINDINGS_FILE).exists() {
+            settings::USER_BINDINGS_FILE
+        } else {
+            settings::BINDINGS_FILE
+        }
+    }
+    pub fn load_current() -> Result<Self, ConfigError> {
+        Bindings::load(Bindings::current_file())
+    }
+    pub fn load(file_path: &str) -> Result<Self, ConfigError> {
+        // the defaults are used until the bindings are saved for the first time
+        if !std::path::Path::new(file_path).exists() {
+            return Ok(Bindings::default());
+        }
+        let file: BindingsFile = config::load(file_path)?;
+        let bindings = file.bindings;
+        if bindings.players.is_empty() {
+            return Err(ConfigError::new(
+                file_path,
+                "bindings.players",
+                "At least one player is required",
+            ));
+        }
+        if let Some((first, second)) = bindings.find_conflict() {
+            return Err(ConfigError::new(
+                file_path,
+                &second.key_path(),
+                &format!(
+                    "Key {} is already bound to {}",
+                    String::from(bindings.get(&second)),
+                    first.key_path()
+                ),
+            ));
+        }
+        if let Some(player) = bindings.mouse_player {
+            if player >= bindings.players.len() {
+                return Err(ConfigError::new(
+                    file_path,
+                    "bindings.mouse-player",
+                    &format!("Player {} has no bindings", player + 1),
+                ));
+            }
+        }
+        Ok(bindings)
+    }
+    pub fn next_mouse_player(&mut self) {
+        self.mouse_player = match self.mouse_player {
+            None if !self.players.is_empty() => Some(0),
+            Some(player) if player + 1 < self.players.len() => Some(player + 1),
+            _ => None,
+        };
+    }
+    pub fn save(&self, file_path: &str) -> Result<(), ConfigError> {
+        let contents = serde_yaml::to_string(&BindingsFileRef { bindings: self })
+            .map_err(|error| ConfigError::new(file_path, "", &error.to_string()))?;
+        std::fs::write(file_path, contents)
+            .map_err(|error| ConfigError::new(file_path, "", &error.to_string()))
+    }
+    pub fn list(&self) -> Vec<Binding> {
+        let mut bindings = Vec::new();
+        for player in 0..self.players.len() {
+            for action in [Action::Left, Action::Right, Action::Launch].iter() {
+                bindings.push(Binding {
+                    player: Some(player),
+                    action: *action,
+                });
+            }
+        }
+        bindings.push(Binding {
+            player: None,
+            action: Action::Pause,
+        });
+        bindings
+    }
+    pub fn get(&self, binding: &Binding) -> Key {
+        match (binding.player, binding.action) {
+            (Some(player), Action::Left) => self.players[player].left,
+            (Some(player), Action::Right) => self.players[player].right,
+            (Some(player), Action::Launch) => self.players[player].launch,
+            _ => self.pause,
+        }
+    }
+    pub fn set(&mut self, binding: &Binding, key: Key) {
+        match (binding.player, binding.action) {
+            (Some(player), Action::Left) => self.players[player].left = key,
+            (Some(player), Action::Right) => self.players[player].right = key,
+            (Some(player), Action::Launch) => self.players[player].launch = key,
+            _ => self.pause = key,
+        }
+    }
+    pub fn find(&self, keycode: KeyCode) -> Option<Binding> {
+        self.list()
+            .into_iter()
+            .find(|binding| self.get(binding).0 == keycode)
+    }
+    pub fn find_conflict(&self) -> Option<(Binding, Binding)> {
+        let bindings = self.list();
+        for (index, second) in bindings.iter().enumerate() {
+            for first in bindings[..index].iter() {
+                if self.get(first) == self.get(second) {
+                    return Some((*first, *second));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::{assert_error, broken_copy, write};
+
+    fn shipped(name: &str, from: &str, to: &str) -> String {
+        broken_copy(settings::BINDINGS_FILE, name, from, to)
+    }
+
+    #[test]
+    fn shipped_bindings_match_the_defaults() {
+        let bindings = Bindings::load(settings::BINDINGS_FILE).unwrap();
+        let defaults = Bindings::default();
+        for binding in defaults.list() {
+            assert!(bindings.get(&binding) == defaults.get(&binding));
+        }
+    }
+
+    #[test]
+    fn unknown_key() {
+        let path = shipped("bad-key.yaml", "launch: \"Up\"", "launch: \"Upwards\"");
+        assert_error(
+            Bindings::load(&path).err(),
+            &path,
+            "bindings.players[0].launch",
+            "Unknown key: Upwards",
+        );
+    }
+
+    #[test]
+    fn key_shared_by_two_players() {
+        let path = shipped("shared-key.yaml", "left: \"A\"", "left: \"Left\"");
+        assert_error(
+            Bindings::load(&path).err(),
+            &path,
+            "bindings.players[1].left",
+            "Key Left is already bound to bindings.players[0].left",
+        );
+    }
+
+    #[test]
+    fn pause_key_bound_to_a_player() {
+        let path = shipped("shared-pause.yaml", "right: \"D\"", "right: \"Escape\"");
+        assert_error(
+            Bindings::load(&path).err(),
+            &path,
+            "bindings.pause",
+            "Key Escape is already bound to bindings.players[1].right",
+        );
+    }
+
+    #[test]
+    fn mouse_player_out_of_range() {
+        let contents = std::fs::read_to_string(settings::BINDINGS_FILE).unwrap();
+        let path = write(
+            "bad-mouse.yaml",
+            &format!("{}  mouse-player: 3\n", contents),
+        );
+        assert_error(
+            Bindings::load(&path).err(),
+            &path,
+            "bindings.mouse-player",
+            "Player 4 has no bindings",
+        );
+        let path = write("mouse.yaml", &format!("{}  mouse-player: 2\n", contents));
+        assert!(Bindings::load(&path).unwrap().mouse_player == Some(2));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn mouse_player_cycles_through_the_players() {
+        let mut bindings = Bindings::default();
+        let mut cycle = Vec::new();
+        for _ in 0..5 {
+            bindings.next_mouse_player();
+            cycle.push(bindings.mouse_player);
+        }
+        assert!(cycle == vec![Some(0), Some(1), Some(2), None, Some(0)]);
+    }
+
+    #[test]
+    fn empty_player_list() {
+        let path = write(
+            "no-players.yaml",
+            "bindings:\n  pause: \"Escape\"\n  players: []\n",
+        );
+        assert_error(
+            Bindings::load(&path).err(),
+            &path,
+            "bindings.players",
+            "At least one player is required",
+        );
+    }
+
+    #[test]
+    fn unwritable_bindings_file() {
+        let path = std::env::temp_dir().join(format!("hexpong-{}-missing", std::process::id()));
+        let path = String::from(path.join("bindings.yaml").to_str().unwrap());
+        let error = Bindings::default().save(&path).err();
+        assert_eq!(error.expect("Expected a config error").path, path);
+    }
+
+    #[test]
+    fn saved_bindings_load_back() {
+        let mut bindings = Bindings::default();
+        bindings.players[2].launch = Key(KeyCode::K);
+        bindings.next_mouse_player();
+        let path = write("saved.yaml", "");
+        bindings.save(&path).unwrap();
+        let loaded = Bindings::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(loaded.players[2].launch == Key(KeyCode::K));
+        assert!(loaded.mouse_player == bindings.mouse_player);
+    }
+}
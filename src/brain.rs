@@ -0,0 +1,256 @@
+use crate::component::{ball, controller, wall};
+use crate::gamemode;
+use crate::settings;
+use crate::InputState;
+use geometry::base::{Point, Vector};
+use geometry::shape::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const MAX_REFLECTIONS: u32 = 8;
+
+pub trait ControllerBrain {
+    // input driving the controller on the next tick, given what the player pressed
+    fn decide(
+        &mut self,
+        controller: &controller::Controller,
+        balls: &[ball::Ball],
+        walls: &[wall::Wall],
+        input: InputState,
+    ) -> InputState;
+}
+
+pub struct Human;
+
+impl ControllerBrain for Human {
+    fn decide(
+        &mut self,
+        _controller: &controller::Controller,
+        _balls: &[ball::Ball],
+        _walls: &[wall::Wall],
+        input: InputState,
+    ) -> InputState {
+        input
+    }
+}
+
+pub struct Ai {
+    difficulty: gamemode::Difficulty,
+    rng: StdRng,
+    target: f32,
+    ticks_left: u32,
+}
+
+impl Ai {
+    pub fn new(difficulty: gamemode::Difficulty, seed: u64) -> Self {
+        Ai {
+            difficulty: difficulty,
+            rng: StdRng::seed_from_u64(seed),
+            target: 0.5,
+            ticks_left: 0,
+        }
+    }
+    fn reaction_ticks(&self) -> u32 {
+        match self.difficulty {
+            gamemode::Difficulty::Easy => 20,
+            gamemode::Difficulty::Normal => 10,
+            gamemode::Difficulty::Hard => 4,
+        }
+    }
+    fn aim_error(&self) -> f32 {
+        // in barpos units, the side length being 1.0
+        match self.difficulty {
+            gamemode::Difficulty::Easy => 0.12,
+            gamemode::Difficulty::Normal => 0.06,
+            gamemode::Difficulty::Hard => 0.02,
+        }
+    }
+}
+
+impl ControllerBrain for Ai {
+    fn decide(
+        &mut self,
+        controller: &controller::Controller,
+        balls: &[ball::Ball],
+        walls: &[wall::Wall],
+        _input: InputState,
+    ) -> InputState {
+        // the AI only looks at the field again once it had time to react
+        if self.ticks_left == 0 {
+            self.ticks_left = self.reaction_ticks();
+            let error = self.aim_error();
+            self.target = match predict(controller, balls, walls) {
+                Some(barpos) => barpos + self.rng.gen_range(-error, error),
+                None => 0.5,
+            };
+        } else {
+            self.ticks_left -= 1;
+        }
+        InputState {
+            target: Some(self.target),
+            ..InputState::default()
+        }
+    }
+}
+
+pub fn create(ai: Option<gamemode::Difficulty>, seed: u64) -> Box<dyn ControllerBrain> {
+    match ai {
+        Some(difficulty) => Box::new(Ai::new(difficulty, seed)),
+        None => Box::new(Human),
+    }
+}
+
+fn side_normal(side: &gamemode::Side) -> Vector {
+    // pointing out of the arena, see Wall::new
+    let phi = side.to_ang().to_radians();
+    Vector {
+        dx: phi.cos(),
+        dy: phi.sin(),
+    }
+}
+
+fn predict(
+    controller: &controller::Controller,
+    balls: &[ball::Ball],
+    walls: &[wall::Wall],
+) -> Option<f32> {
+    // follows every ball through its wall reflections up to the first of the controller's bars
+    let apothem = 3.0f32.sqrt() / 2.0 * settings::UNIT_SIZE;
+    let mut earliest: Option<(f32, f32)> = None;
+    for ball in balls.iter() {
+        let mut position = ball.shape.center();
+        let mut velocity = ball.velocity;
+        let mut elapsed = 0.0;
+        for _ in 0..MAX_REFLECTIONS {
+            let mut next: Option<(f32, gamemode::Side, Vector)> = None;
+            for side in [
+                gamemode::Side::Bottom,
+                gamemode::Side::BottomLeft,
+                gamemode::Side::BottomRight,
+                gamemode::Side::Top,
+                gamemode::Side::TopLeft,
+                gamemode::Side::TopRight,
+            ]
+            .iter()
+            {
+                let normal = side_normal(side);
+                let approach = velocity.dot(normal);
+                if approach <= 0.0 {
+                    continue;
+                }
+                let distance = apothem
+                    - ball.shape.radius()
+                    - (position.x * normal.dx + position.y * normal.dy);
+                let time = (distance / approach).max(0.0);
+                if next
+                    .as_ref()
+                    .map_or(true, |(earliest, _, _)| time < *earliest)
+                {
+                    next = Some((time, side.clone(), normal));
+                }
+            }
+            let (time, side, normal) = match next {
+                Some(next) => next,
+                None => break,
+            };
+            position = Point::new(
+                position.x + velocity.dx * time,
+                position.y + velocity.dy * time,
+            );
+            elapsed += time;
+            if let Some(bar) = controller.bars.iter().find(|bar| bar.side == side) {
+                if earliest.map_or(true, |(time, _)| elapsed < time) {
+                    earliest = Some((elapsed, bar.project(position.x, position.y)));
+                }
+                break;
+            }
+            // the ball is heading for another player
+            if !walls.iter().any(|wall| wall.side == side) {
+                break;
+            }
+            velocity = velocity - normal * (2.0 * velocity.dot(normal));
+        }
+    }
+    earliest.map(|(_, barpos)| barpos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation;
+
+    fn reflected_once() -> (simulation::Simulation, usize, f32) {
+        // the AI plays the top side, with walls on both sides of it
+        let mode = gamemode::GameMode::new(
+            "config/gamemodes/versus-ai.yaml",
+            gamemode::Difficulty::Normal,
+        )
+        .unwrap();
+        let mut simulation = simulation::Simulation::new(mode, 0, 1);
+        let ai = simulation
+            .players
+            .iter()
+            .position(|player| player.ai.is_some())
+            .unwrap();
+        // moving right, the ball bounces off the bottom right wall towards 240 degrees and
+        // reaches the top side, which the ball center gets within a radius of at y = -distance
+        let ball = &mut simulation.balls[0];
+        let distance = 3.0f32.sqrt() / 2.0 * settings::UNIT_SIZE - ball.shape.radius();
+        let y = distance / 4.0;
+        ball.shape.move_to(Point::new(0.0, y));
+        ball.velocity = Vector {
+            dx: ball.velocity.magnitude(),
+            dy: 0.0,
+        };
+        let x = (distance / 2.0 - y) / (3.0f32.sqrt() / 2.0);
+        let bar = simulation.players[ai]
+            .bars
+            .iter()
+            .find(|bar| bar.side == gamemode::Side::Top)
+            .unwrap();
+        let expected = bar.project(x, -distance);
+        (simulation, ai, expected)
+    }
+
+    #[test]
+    fn predicts_the_intercept_after_a_wall_reflection() {
+        let (simulation, ai, expected) = reflected_once();
+        let barpos = predict(
+            &simulation.players[ai],
+            &simulation.balls,
+            &simulation.walls,
+        )
+        .unwrap();
+        assert!((barpos - expected).abs() < 1e-3);
+        // off center, so it is no fallback to the middle of the side
+        assert!((barpos - 0.5).abs() > 0.2);
+    }
+
+    #[test]
+    fn ai_aims_within_its_error_and_reacts_with_its_delay() {
+        for &difficulty in [
+            gamemode::Difficulty::Easy,
+            gamemode::Difficulty::Normal,
+            gamemode::Difficulty::Hard,
+        ]
+        .iter()
+        {
+            let (simulation, ai, expected) = reflected_once();
+            let controller = &simulation.players[ai];
+            let mut brain = Ai::new(difficulty, 5);
+            let mut decide = |balls: &[ball::Ball]| {
+                brain
+                    .decide(controller, balls, &simulation.walls, InputState::default())
+                    .target
+                    .unwrap()
+            };
+            let target = decide(&simulation.balls);
+            assert!((target - expected).abs() <= Ai::new(difficulty, 5).aim_error());
+            // without balls the AI returns to the middle, but only once it reacts
+            for _ in 0..Ai::new(difficulty, 5).reaction_ticks() {
+                assert_eq!(decide(&[]), target);
+            }
+            assert_eq!(decide(&[]), 0.5);
+        }
+    }
+}
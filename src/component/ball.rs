@@ -1,11 +1,12 @@
-use crate::converter;
+use crate::component::controller;
 use crate::settings;
+use crate::sweep;
 use crate::themes;
-use crate::VisualComponent;
+use crate::{PhysicalComponent, VisualComponent};
 use geometry::base::{Angle, Point, Vector};
-use geometry::collision::*;
 use geometry::shape::*;
 use ggez::*;
+use rand::rngs::StdRng;
 use rand::Rng;
 
 pub struct Ball {
@@ -13,47 +14,145 @@ pub struct Ball {
     // pub velocity: f32,
     // pub direction: f32,
     pub velocity: Vector,
+    pub owner: Option<controller::Player>,
+    // index of the owner's bar that last deflected the ball
+    pub last_bar: Option<usize>,
+    // ticks since the ball last touched a bar or a destructible block
+    pub idle_ticks: u32,
+    bounces: Vec<(i32, i32)>,
     mesh: Option<graphics::Mesh>,
 }
 
+const BOUNCE_HISTORY: usize = 24;
+const BOUNCE_SPOT_SIZE: f32 = 5.0;
+
 impl Ball {
-    pub fn new(ball_speed: f32) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn new(position: Point, ball_speed: f32, rng: &mut StdRng) -> Self {
         let var = (rng.gen::<f64>() - 0.5) * 20.0;
 
         Ball {
-            shape: Circle::new(
-                Point::new(settings::BALL_SPAWN.0, settings::BALL_SPAWN.1),
-                settings::norm_to_unit(0.01),
-            ),
+            shape: Circle::new(position, settings::norm_to_unit(0.01)),
             velocity: Vector::from_magnitude(
                 settings::norm_to_unit(ball_speed),
                 Angle::new(270f64 + var),
             ),
+            owner: None,
+            last_bar: None,
+            idle_ticks: 0,
+            bounces: Vec::new(),
+            mesh: None,
+        }
+    }
+    pub fn split(&self, position: Point, direction: Vector) -> Self {
+        // a new ball of the same size, speed and owner, heading in the given direction
+        let length = direction.magnitude();
+        let velocity = if length > 0.0 {
+            direction * (self.velocity.magnitude() / length)
+        } else {
+            self.velocity
+        };
+        Ball {
+            shape: Circle::new(position, self.shape.radius()),
+            velocity: velocity,
+            owner: self.owner,
+            last_bar: self.last_bar,
+            idle_ticks: 0,
+            bounces: Vec::new(),
             mesh: None,
         }
     }
+    pub fn record_progress(&mut self) {
+        self.idle_ticks = 0;
+        self.bounces.clear();
+    }
+    pub fn record_bounce(&mut self) -> usize {
+        // returns how many times the ball already bounced off the same spot
+        let spot = (
+            (self.shape.center().x / BOUNCE_SPOT_SIZE).round() as i32,
+            (self.shape.center().y / BOUNCE_SPOT_SIZE).round() as i32,
+        );
+        let visits = self
+            .bounces
+            .iter()
+            .filter(|&&bounce| bounce == spot)
+            .count();
+        self.bounces.push(spot);
+        if self.bounces.len() > BOUNCE_HISTORY {
+            self.bounces.remove(0);
+        }
+        visits
+    }
+    pub fn nudge(&mut self, angle: f64) {
+        self.velocity = Vector::from_magnitude(
+            self.velocity.magnitude(),
+            Angle::new(self.velocity.orientation().deg + angle),
+        );
+        self.record_progress();
+    }
     pub fn bounce_away(&mut self, norm_vec: Vector) {
         self.velocity = self.velocity - norm_vec * self.velocity.dot(norm_vec) * 2.0;
     }
-    fn get_position(&self) -> mint::Point2<f32> {
-        converter::convert_to_point(&self.shape.center())
+    pub fn mass(&self) -> f32 {
+        self.shape.radius() * self.shape.radius()
+    }
+    pub fn bounce_off(&mut self, other: &mut Ball, norm_vec: Vector) {
+        // norm_vec points from the other ball towards this one
+        let total_mass = self.mass() + other.mass();
+        let approach = (self.velocity - other.velocity).dot(norm_vec);
+        if approach < 0.0 {
+            let impulse = -2.0 * approach * self.mass() * other.mass() / total_mass;
+            self.velocity = self.velocity + norm_vec * (impulse / self.mass());
+            other.velocity = other.velocity - norm_vec * (impulse / other.mass());
+        }
+        // separate the balls, so they do not stick together on the next tick
+        let offset = Vector {
+            dx: self.shape.center().x - other.shape.center().x,
+            dy: self.shape.center().y - other.shape.center().y,
+        };
+        let overlap = self.shape.radius() + other.shape.radius() - offset.magnitude();
+        if overlap > 0.0 {
+            self.shape
+                .translate(norm_vec * (overlap * other.mass() / total_mass));
+            other
+                .shape
+                .translate(norm_vec * (-overlap * self.mass() / total_mass));
+        }
     }
 }
 
-impl VisualComponent for Ball {
-    fn collision(&self, ball: &Ball) -> Option<Vector> {
-        // TODO - Ball-ball collision
-        None
+impl PhysicalComponent for Ball {
+    fn collision(&self, ball: &Ball, _motion: Vector) -> Option<sweep::Impact> {
+        // balls are checked for overlap once all of them have moved
+        let offset = Vector {
+            dx: self.shape.center().x - ball.shape.center().x,
+            dy: self.shape.center().y - ball.shape.center().y,
+        };
+        let dist = offset.magnitude();
+        let depth = self.shape.radius() + ball.shape.radius() - dist;
+        if depth <= 0.0 {
+            return None;
+        }
+        Some(sweep::Impact {
+            time: 0.0,
+            norm_vec: if dist > 0.0 {
+                offset * (1.0 / dist)
+            } else {
+                Vector { dx: 0.0, dy: -1.0 }
+            },
+            depth: depth,
+        })
+    }
+    fn update(&mut self) {
+        self.shape
+            .translate(self.velocity * settings::TICK_DURATION);
     }
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
+}
+
+impl VisualComponent for Ball {
+    fn draw(&mut self, ctx: &mut Context, _theme: &themes::Theme) -> GameResult {
         if self.mesh == None {
             self.mesh = self.create_mesh(ctx);
         }
-        self.shape.translate(self.velocity);
-        Ok(())
-    }
-    fn draw(&self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
         if let Some(circle) = &self.mesh {
             graphics::draw(
                 ctx,
@@ -106,3 +205,139 @@ impl VisualComponent for Ball {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn ball(x: f32, y: f32, radius: f32, velocity: Vector) -> Ball {
+        let mut ball = Ball::new(Point::new(x, y), 1.0, &mut StdRng::seed_from_u64(0));
+        ball.shape = Circle::new(Point::new(x, y), radius);
+        ball.velocity = velocity;
+        ball
+    }
+
+    fn momentum(balls: &[&Ball]) -> Vector {
+        balls
+            .iter()
+            .fold(Vector { dx: 0.0, dy: 0.0 }, |total, ball| {
+                total + ball.velocity * ball.mass()
+            })
+    }
+
+    fn energy(balls: &[&Ball]) -> f32 {
+        balls
+            .iter()
+            .map(|ball| 0.5 * ball.mass() * ball.velocity.dot(ball.velocity))
+            .sum()
+    }
+
+    fn collide(first: &mut Ball, second: &mut Ball) {
+        let impact = first
+            .collision(second, first.velocity * settings::TICK_DURATION)
+            .unwrap();
+        first.bounce_off(second, impact.norm_vec);
+    }
+
+    fn assert_conserved(mut first: Ball, mut second: Ball) -> (Ball, Ball) {
+        let momentum_before = momentum(&[&first, &second]);
+        let energy_before = energy(&[&first, &second]);
+        collide(&mut first, &mut second);
+        let momentum_after = momentum(&[&first, &second]);
+        let energy_after = energy(&[&first, &second]);
+        let scale = momentum_before.magnitude().max(1.0);
+        assert!((momentum_after.dx - momentum_before.dx).abs() / scale < 1e-4);
+        assert!((momentum_after.dy - momentum_before.dy).abs() / scale < 1e-4);
+        assert!((energy_after - energy_before).abs() / energy_before < 1e-4);
+        (first, second)
+    }
+
+    #[test]
+    fn split_keeps_the_speed() {
+        let original = ball(0.0, 0.0, 10.0, Vector { dx: 3.0, dy: 4.0 });
+        let split = original.split(Point::new(5.0, 5.0), Vector { dx: 0.0, dy: -2.0 });
+        assert!((split.velocity.dx - 0.0).abs() < 1e-4);
+        assert!((split.velocity.dy + 5.0).abs() < 1e-4);
+        // without a direction it follows the original
+        let split = original.split(Point::new(5.0, 5.0), Vector { dx: 0.0, dy: 0.0 });
+        assert!((split.velocity.dx - 3.0).abs() < 1e-4);
+        assert!((split.velocity.dy - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn head_on_collision_of_equal_balls_swaps_velocities() {
+        let (first, second) = assert_conserved(
+            ball(-9.0, 0.0, 10.0, Vector { dx: 300.0, dy: 0.0 }),
+            ball(
+                9.0,
+                0.0,
+                10.0,
+                Vector {
+                    dx: -300.0,
+                    dy: 0.0,
+                },
+            ),
+        );
+        assert!((first.velocity.dx + 300.0).abs() < 1e-3);
+        assert!((second.velocity.dx - 300.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn oblique_collision_of_unequal_balls_conserves_momentum_and_energy() {
+        assert_conserved(
+            ball(
+                -8.0,
+                -6.0,
+                10.0,
+                Vector {
+                    dx: 450.0,
+                    dy: 120.0,
+                },
+            ),
+            ball(
+                5.0,
+                4.0,
+                15.0,
+                Vector {
+                    dx: -90.0,
+                    dy: 240.0,
+                },
+            ),
+        );
+        assert_conserved(
+            ball(0.0, 0.0, 12.0, Vector { dx: 0.0, dy: 600.0 }),
+            ball(7.0, 18.0, 8.0, Vector { dx: 0.0, dy: 0.0 }),
+        );
+    }
+
+    #[test]
+    fn overlapping_balls_end_up_separated_and_moving_apart() {
+        let mut first = ball(
+            -3.0,
+            1.0,
+            10.0,
+            Vector {
+                dx: 200.0,
+                dy: 50.0,
+            },
+        );
+        let mut second = ball(
+            4.0,
+            -2.0,
+            15.0,
+            Vector {
+                dx: -100.0,
+                dy: 0.0,
+            },
+        );
+        collide(&mut first, &mut second);
+        let offset = Vector {
+            dx: first.shape.center().x - second.shape.center().x,
+            dy: first.shape.center().y - second.shape.center().y,
+        };
+        let radii = first.shape.radius() + second.shape.radius();
+        assert!(offset.magnitude() >= radii - 1e-3);
+        assert!((first.velocity - second.velocity).dot(offset) > 0.0);
+    }
+}
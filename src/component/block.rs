@@ -1,44 +1,61 @@
 use crate::component::ball;
 use crate::converter;
 use crate::settings;
+use crate::sweep;
 use crate::themes;
-use crate::{AudibleComponent, VisualComponent};
+use crate::{PhysicalComponent, VisualComponent};
 use geometry::base::{Angle, Point, Vector};
-use geometry::collision;
 use geometry::shape::*;
-use ggez::audio::*;
 use ggez::*;
+use serde::Deserialize;
+use std::convert::TryFrom;
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
 pub enum BlockType {
     Basic,
     Basic2,
     Basic3,
     Immortal,
+    Explosive,
+    Multiball,
 }
 
 impl BlockType {
-    pub fn from_str(input: &str) -> BlockType {
+    pub fn from_str(input: &str) -> Option<BlockType> {
         match &input.to_lowercase()[..] {
-            "basic" => BlockType::Basic,
-            "basic2" => BlockType::Basic2,
-            "basic3" => BlockType::Basic3,
-            "immortal" => BlockType::Immortal,
-            _ => panic!("Invalid block shape"),
+            "basic" => Some(BlockType::Basic),
+            "basic2" => Some(BlockType::Basic2),
+            "basic3" => Some(BlockType::Basic3),
+            "immortal" => Some(BlockType::Immortal),
+            "explosive" => Some(BlockType::Explosive),
+            "multiball" => Some(BlockType::Multiball),
+            _ => None,
         }
     }
 }
 
+impl TryFrom<String> for BlockType {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        BlockType::from_str(&input).ok_or_else(|| format!("Invalid block type: {}", input))
+    }
+}
+
+#[derive(Clone)]
 pub struct Block {
     pub shape: Hexagon,
+    pub index: GridIndex,
     pub block_type: BlockType,
     mesh: Option<graphics::Mesh>,
 }
 
 impl Block {
-    pub fn new(x: f32, y: f32, r: f32, block_type: BlockType) -> Self {
+    pub fn new(index: GridIndex, r: f32, block_type: BlockType) -> Self {
+        let point = index.to_unit(r);
         Block {
-            shape: Hexagon::new(Point::new(x, y), r, Angle::new(90f64)),
+            shape: Hexagon::new(Point::new(point.x, point.y), r, Angle::new(90f64)),
+            index: index,
             block_type: block_type,
             mesh: None,
         }
@@ -55,29 +72,31 @@ impl Block {
                 false
             }
             BlockType::Immortal => false,
-            _ => true,
+            BlockType::Explosive => true,
+            BlockType::Multiball => true,
+        }
+    }
+    pub fn blast(&mut self, destroy_immortal: bool) -> bool {
+        // caught in an explosion, which counts as a regular hit for mortal blocks
+        match self.block_type {
+            BlockType::Immortal => destroy_immortal,
+            _ => self.hit(),
         }
     }
 }
 
-impl VisualComponent for Block {
-    fn collision(&self, ball: &ball::Ball) -> Option<Vector> {
-        if collision::detection::are_close(&self.shape, &ball.shape, 10.0) {
-            let (dist, _, _) =
-                collision::detection::distance_closest_points(&self.shape, &ball.shape);
-            if dist < 5.0 {
-                return collision::detection::ball_bounce(&ball.shape, ball.velocity, &self.shape);
-            }
-        }
-        None
+impl PhysicalComponent for Block {
+    fn collision(&self, ball: &ball::Ball, motion: Vector) -> Option<sweep::Impact> {
+        sweep::sweep_polygon(&ball.shape, motion, &self.shape.polygon())
     }
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
+    fn update(&mut self) {}
+}
+
+impl VisualComponent for Block {
+    fn draw(&mut self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
         if self.mesh == None {
             self.mesh = self.create_mesh(ctx);
         }
-        Ok(())
-    }
-    fn draw(&self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
         let location = self.shape.center();
         if let Some(polygon) = &self.mesh {
             graphics::draw(
@@ -118,17 +137,9 @@ impl VisualComponent for Block {
     }
 }
 
-impl AudibleComponent for Block {
-    fn play_sound(&self, ctx: &mut Context) {
-        ggez::audio::Source::new(ctx, "/impactGlass_medium_000.ogg")
-            .unwrap()
-            .play_detached()
-            .unwrap();
-    }
-}
-
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GridIndex {
     pub q: i32,
     pub r: i32,
@@ -147,4 +158,38 @@ impl GridIndex {
         let y = (3.0 / 2.0 * self.r as f32) * tile_radius;
         mint::Point2 { x: x, y: y }
     }
+    pub fn from_unit(x: f32, y: f32, tile_radius: f32) -> GridIndex {
+        let q = (3.0f32.sqrt() / 3.0 * x - y / 3.0) / tile_radius;
+        let r = (2.0 / 3.0 * y) / tile_radius;
+        // round in cube coordinates, fixing the component with the largest rounding error
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        GridIndex {
+            q: rq as i32,
+            r: rr as i32,
+        }
+    }
+    pub fn distance(&self, other: &GridIndex) -> i32 {
+        let dq = self.q - other.q;
+        let dr = self.r - other.r;
+        (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+    }
+    pub fn range(&self, distance: i32) -> Vec<GridIndex> {
+        let mut cells = Vec::new();
+        for dq in -distance..=distance {
+            for dr in (-distance).max(-dq - distance)..=distance.min(-dq + distance) {
+                cells.push(GridIndex {
+                    q: self.q + dq,
+                    r: self.r + dr,
+                });
+            }
+        }
+        cells
+    }
 }
@@ -1,59 +1,107 @@
 use crate::component::ball;
+use crate::converter;
 use crate::gamemode;
 use crate::settings;
+use crate::sweep;
 use crate::themes;
 use crate::InputState;
-use crate::{AudibleComponent, VisualComponent};
+use crate::{PhysicalComponent, VisualComponent};
 use geometry::base::*;
-use geometry::collision;
 use geometry::shape::*;
-use ggez::audio::*;
 use ggez::*;
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum Player {
     Player1,
     Player2,
     Player3,
 }
 
+impl Player {
+    pub fn index(&self) -> usize {
+        match self {
+            Player::Player1 => 0,
+            Player::Player2 => 1,
+            Player::Player3 => 2,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Controller {
     pub barpos: f32,
     pub bars: Vec<Bar>,
     pub bar_size: f32,
+    pub movement: gamemode::BarMovement,
     pub input: InputState,
     pub player: Player,
+    // difficulty of the AI driving this controller, none for human players
+    pub ai: Option<gamemode::Difficulty>,
+    pub score: i32,
+    pub lives: u32,
+    // change of barpos per second
+    pub velocity: f32,
 }
 
 impl Controller {
-    pub fn new(bar_size: f32, player: Player) -> Self {
+    pub fn new(bar_size: f32, movement: gamemode::BarMovement, player: Player) -> Self {
         Controller {
             barpos: 0.5,
             bar_size: bar_size,
+            movement: movement,
             bars: Vec::new(),
             input: InputState::default(),
             player: player,
+            ai: None,
+            score: 0,
+            lives: 0,
+            velocity: 0.0,
         }
     }
-    pub fn update(&mut self, ctx: &mut Context) -> GameResult {
-        if self.input.left {
-            self.barpos -= 0.03; // TODO as parameter
-        } else if self.input.right {
-            self.barpos += 0.03;
+    pub fn update(&mut self) {
+        if let Some(target) = self.input.target {
+            // pointing inputs move straight towards the target, limited only by the speed
+            let target = target
+                .max(self.bar_size / 2.0)
+                .min(1.0 - self.bar_size / 2.0);
+            let step = self.movement.speed * settings::TICK_DURATION;
+            let previous = self.barpos;
+            self.barpos += (target - self.barpos).max(-step).min(step);
+            self.velocity = (self.barpos - previous) / settings::TICK_DURATION;
+        } else {
+            self.accelerate();
+            self.barpos += self.velocity * settings::TICK_DURATION;
         }
         if self.barpos < (0.0 + self.bar_size / 2.0) {
             self.barpos = 0.0 + self.bar_size / 2.0;
+            self.velocity = self.velocity.max(0.0);
         }
         if self.barpos > (1.0 - self.bar_size / 2.0) {
             self.barpos = 1.0 - self.bar_size / 2.0;
+            self.velocity = self.velocity.min(0.0);
         }
         for bar in self.bars.iter_mut() {
             bar.pos = self.barpos;
-            bar.update(ctx)?;
+            bar.velocity = bar.axis() * (self.velocity * settings::UNIT_SIZE);
+            bar.update();
         }
-        Ok(())
     }
-    pub fn draw(&self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
-        for bar in self.bars.iter() {
+    fn accelerate(&mut self) {
+        let direction = self.input.right.max(0.0).min(1.0) - self.input.left.max(0.0).min(1.0);
+        let (target, rate) = if direction != 0.0 {
+            (direction * self.movement.speed, self.movement.acceleration)
+        } else {
+            (0.0, self.movement.friction)
+        };
+        let change = rate * settings::TICK_DURATION;
+        self.velocity = if self.velocity < target {
+            (self.velocity + change).min(target)
+        } else {
+            (self.velocity - change).max(target)
+        };
+    }
+    pub fn draw(&mut self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
+        for bar in self.bars.iter_mut() {
             bar.draw(ctx, theme)?;
         }
         Ok(())
@@ -62,12 +110,14 @@ impl Controller {
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+#[derive(Clone)]
 pub struct Bar {
     pub shape: Rectangle,
     pub side: gamemode::Side,
     pos: f32,
+    pub velocity: Vector,
     mesh: Option<graphics::Mesh>,
-    owner: Player,
+    pub owner: Player,
     reversed: bool,
 }
 
@@ -80,69 +130,87 @@ impl Bar {
                     settings::norm_to_unit(bar_size),
                     settings::norm_to_unit(0.02),
                 ),
-                Angle::new(side.to_ang() as f64 - 60f64),
+                Angle::new(side.to_ang() as f64 + 90f64),
             ),
             pos: 0.5,
+            velocity: Vector { dx: 0.0, dy: 0.0 },
             side: side.clone(),
             mesh: None,
             owner: owner,
             reversed: reversed,
         }
     }
-    fn get_vertices(&self) -> [mint::Point2<f32>; 4] {
-        let mut vertices: [mint::Point2<f32>; 4] = [mint::Point2 { x: 0.0, y: 0.0 }; 4];
-        let phi = (self.side.to_ang() - 60.0).to_radians();
-
-        let long_cos = self.shape.size().w / 2.0 * phi.cos();
-        let long_sin = self.shape.size().w / 2.0 * phi.sin();
-        let short_cos = self.shape.size().h / 2.0 * phi.cos();
-        let short_sin = self.shape.size().h / 2.0 * phi.sin();
-
-        vertices[0].x = long_cos + short_sin;
-        vertices[0].y = -long_sin + short_cos;
-        vertices[1].x = long_cos - short_sin;
-        vertices[1].y = -long_sin - short_cos;
-        vertices[2].x = -long_cos - short_sin;
-        vertices[2].y = long_sin - short_cos;
-        vertices[3].x = -long_cos + short_sin;
-        vertices[3].y = long_sin + short_cos;
-
-        vertices
+    pub fn axis(&self) -> Vector {
+        // direction in which the bar moves when barpos increases
+        let phi = self.side.to_ang().to_radians();
+        let sign = if self.reversed { -1.0 } else { 1.0 };
+        Vector {
+            dx: sign * phi.sin(),
+            dy: -sign * phi.cos(),
+        }
     }
-}
-
-impl VisualComponent for Bar {
-    fn collision(&self, ball: &ball::Ball) -> Option<Vector> {
-        if collision::detection::are_close(&self.shape, &ball.shape, 10.0) {
-            let (dist, _, _) =
-                collision::detection::distance_closest_points(&self.shape, &ball.shape);
-            if dist < 5.0 {
-                return collision::detection::ball_bounce(&ball.shape, ball.velocity, &self.shape);
-            }
+    pub fn project(&self, x: f32, y: f32) -> f32 {
+        // barpos at which the bar center is closest to the given point
+        let phi = self.side.to_ang().to_radians();
+        let yc0 = 3.0f32.sqrt() / 2.0 * settings::UNIT_SIZE;
+        let axis = self.axis();
+        0.5 + ((x - yc0 * phi.cos()) * axis.dx + (y - yc0 * phi.sin()) * axis.dy)
+            / settings::UNIT_SIZE
+    }
+    pub fn inward_normal(&self) -> Vector {
+        // facing the arena center
+        let phi = self.side.to_ang().to_radians();
+        Vector {
+            dx: -phi.cos(),
+            dy: -phi.sin(),
         }
-        None
     }
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        if self.mesh == None {
-            self.mesh = self.create_mesh(ctx);
+    pub fn deflect(&self, ball: &mut ball::Ball, english: &gamemode::English) {
+        let axis = self.axis();
+        let center = self.shape.center();
+        let normal = self.inward_normal();
+        let speed = ball.velocity.magnitude();
+        let normal_speed = ball.velocity.dot(normal);
+        if normal_speed <= 0.0 || speed == 0.0 {
+            return;
         }
+        let offset = ((ball.shape.center().x - center.x) * axis.dx
+            + (ball.shape.center().y - center.y) * axis.dy)
+            / (self.shape.size().w / 2.0);
+        let tangent_speed =
+            ball.velocity.dot(axis) + self.velocity.dot(axis) * english.velocity_transfer;
+        let angle = (tangent_speed.atan2(normal_speed).to_degrees()
+            + offset.max(-1.0).min(1.0) * english.offset_deflection)
+            .max(-english.max_deflection)
+            .min(english.max_deflection)
+            .to_radians();
+        ball.velocity = (normal * angle.cos() + axis * angle.sin()) * speed;
+    }
+}
 
-        let mut xc0 = -settings::UNIT_SIZE / 2.0 + self.pos * settings::UNIT_SIZE;
+impl PhysicalComponent for Bar {
+    fn collision(&self, ball: &ball::Ball, motion: Vector) -> Option<sweep::Impact> {
+        sweep::sweep_polygon(&ball.shape, motion, &self.shape.polygon())
+    }
+    fn update(&mut self) {
+        // the side center as in Wall::new, shifted along the side by barpos
+        let xc0 = (self.pos - 0.5) * settings::UNIT_SIZE;
         let yc0 = 3.0f32.sqrt() / 2.0 * settings::UNIT_SIZE;
-
-        if self.reversed {
-            xc0 = -xc0;
-        }
-
-        let phi = self.shape.orientation().to_rad32();
+        let phi = self.side.to_ang().to_radians();
+        let axis = self.axis();
 
         self.shape.move_to(Point::new(
-            xc0 * phi.cos() + yc0 * phi.sin(),
-            -xc0 * phi.sin() + yc0 * phi.cos(),
+            yc0 * phi.cos() + xc0 * axis.dx,
+            yc0 * phi.sin() + xc0 * axis.dy,
         ));
-        Ok(())
     }
-    fn draw(&self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
+}
+
+impl VisualComponent for Bar {
+    fn draw(&mut self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
+        if self.mesh == None {
+            self.mesh = self.create_mesh(ctx);
+        }
         if let Some(polygon) = &self.mesh {
             graphics::draw(
                 ctx,
@@ -155,18 +223,16 @@ impl VisualComponent for Bar {
                     0.0,
                     mint::Point2 { x: 0.0, y: 0.0 },
                     settings::get_scale_vector(),
-                    match self.owner {
-                        Player::Player1 => theme.player1,
-                        Player::Player2 => theme.player2,
-                        Player::Player3 => theme.player3,
-                    },
+                    theme.get_player_color(&self.owner),
                 )),
             )?;
         }
         Ok(())
     }
     fn create_mesh(&mut self, ctx: &mut Context) -> Option<graphics::Mesh> {
-        let vertices = self.get_vertices();
+        let mut shape = self.shape.clone();
+        shape.move_to(Point::zero());
+        let vertices = converter::convert_to_points(&shape.polygon());
         Some(
             graphics::Mesh::new_polygon(
                 ctx,
@@ -179,11 +245,56 @@ impl VisualComponent for Bar {
     }
 }
 
-impl AudibleComponent for Bar {
-    fn play_sound(&self, ctx: &mut Context) {
-        ggez::audio::Source::new(ctx, "/back_003.ogg")
-            .unwrap()
-            .play_detached()
-            .unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::wall;
+
+    const SIDES: [gamemode::Side; 6] = [
+        gamemode::Side::Bottom,
+        gamemode::Side::BottomLeft,
+        gamemode::Side::BottomRight,
+        gamemode::Side::Top,
+        gamemode::Side::TopLeft,
+        gamemode::Side::TopRight,
+    ];
+
+    #[test]
+    fn centered_bar_sits_where_the_wall_of_its_side_would() {
+        for side in SIDES.iter() {
+            for &reversed in [false, true].iter() {
+                let mut bar = Bar::new(side, 0.2, Player::Player1, reversed);
+                bar.pos = 0.5;
+                bar.update();
+                let wall = wall::Wall::new(side);
+                let (bar_center, wall_center) = (bar.shape.center(), wall.shape.center());
+                assert!(
+                    (bar_center.x - wall_center.x).abs() < 1e-3,
+                    "{}",
+                    side.to_str()
+                );
+                assert!(
+                    (bar_center.y - wall_center.y).abs() < 1e-3,
+                    "{}",
+                    side.to_str()
+                );
+                assert!(gamemode::Side::from_position(bar_center.x, bar_center.y) == *side);
+            }
+        }
+    }
+
+    #[test]
+    fn bar_moves_along_its_side_and_projects_back() {
+        for side in SIDES.iter() {
+            let mut bar = Bar::new(side, 0.2, Player::Player1, false);
+            for &pos in [0.1, 0.3, 0.9].iter() {
+                bar.pos = pos;
+                bar.update();
+                let center = bar.shape.center();
+                assert!(bar.inward_normal().dot(bar.axis()).abs() < 1e-6);
+                assert!((bar.project(center.x, center.y) - pos).abs() < 1e-4);
+                assert!(gamemode::Side::from_position(center.x, center.y) == *side);
+            }
+        }
     }
 }
@@ -2,14 +2,14 @@ use crate::component::ball;
 use crate::converter;
 use crate::gamemode;
 use crate::settings;
+use crate::sweep;
 use crate::themes;
-use crate::{AudibleComponent, VisualComponent};
+use crate::{PhysicalComponent, VisualComponent};
 use geometry::base::{Angle, Point, Size, Vector};
-use geometry::collision;
 use geometry::shape::*;
-use ggez::audio::*;
 use ggez::*;
 
+#[derive(Clone)]
 pub struct Wall {
     pub shape: Rectangle,
     pub side: gamemode::Side,
@@ -18,7 +18,7 @@ pub struct Wall {
 
 impl Wall {
     pub fn new(side: &gamemode::Side) -> Self {
-        let phi = side.to_ang() + 30.0;
+        let phi = side.to_ang();
         let phi_rad = phi.to_radians();
         Wall {
             shape: Rectangle::new(
@@ -35,24 +35,18 @@ impl Wall {
     }
 }
 
-impl VisualComponent for Wall {
-    fn collision(&self, ball: &ball::Ball) -> Option<Vector> {
-        if collision::detection::are_close(&self.shape, &ball.shape, 10.0) {
-            let (dist, _, _) =
-                collision::detection::distance_closest_points(&self.shape, &ball.shape);
-            if dist < 5.0 {
-                return collision::detection::ball_bounce(&ball.shape, ball.velocity, &self.shape);
-            }
-        }
-        None
+impl PhysicalComponent for Wall {
+    fn collision(&self, ball: &ball::Ball, motion: Vector) -> Option<sweep::Impact> {
+        sweep::sweep_polygon(&ball.shape, motion, &self.shape.polygon())
     }
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
+    fn update(&mut self) {}
+}
+
+impl VisualComponent for Wall {
+    fn draw(&mut self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
         if self.mesh == None {
             self.mesh = self.create_mesh(ctx);
         }
-        Ok(())
-    }
-    fn draw(&self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
         if let Some(polygon) = &self.mesh {
             graphics::draw(
                 ctx,
@@ -91,12 +85,3 @@ impl VisualComponent for Wall {
         )
     }
 }
-
-impl AudibleComponent for Wall {
-    fn play_sound(&self, ctx: &mut Context) {
-        ggez::audio::Source::new(ctx, "/impactMetal_medium_003.ogg")
-            .unwrap()
-            .play_detached()
-            .unwrap();
-    }
-}
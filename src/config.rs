@@ -0,0 +1,324 @@
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: String,
+    pub key: String,
+    pub reason: String,
+}
+
+impl ConfigError {
+    pub fn new(path: &str, key: &str, reason: &str) -> Self {
+        ConfigError {
+            path: String::from(path),
+            key: String::from(key),
+            reason: String::from(reason),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}: {}", self.path, self.reason)
+        } else {
+            write!(f, "{}: {}: {}", self.path, self.key, self.reason)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    pub fn from_path(file_path: &str) -> Option<Format> {
+        let extension = Path::new(file_path).extension()?.to_str()?;
+        match &extension.to_lowercase()[..] {
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_config_file(file_path: &str) -> bool {
+    Format::from_path(file_path).is_some()
+}
+
+pub fn load<T: DeserializeOwned>(file_path: &str) -> Result<T, ConfigError> {
+    let format = Format::from_path(file_path)
+        .ok_or_else(|| ConfigError::new(file_path, "", "Unsupported file extension"))?;
+    let contents = std::fs::read_to_string(file_path)
+        .map_err(|e| ConfigError::new(file_path, "", &format!("Unable to read file ({})", e)))?;
+    // parsed into a generic value first, so that the key path of an invalid entry is known
+    match format {
+        Format::Yaml => {
+            let value: serde_yaml::Value = serde_yaml::from_str(&contents)
+                .map_err(|e| ConfigError::new(file_path, "", &format!("Invalid YAML ({})", e)))?;
+            deserialize(file_path, value)
+        }
+        Format::Toml => {
+            let value: toml::Value = toml::from_str(&contents)
+                .map_err(|e| ConfigError::new(file_path, "", &format!("Invalid TOML ({})", e)))?;
+            // toml appends its own key to the reason, without the list indices
+            deserialize(file_path, value).map_err(|mut e| {
+                if let Some(index) = e.reason.find(" for key `") {
+                    e.reason.truncate(index);
+                }
+                e
+            })
+        }
+        Format::Json => {
+            let value: serde_json::Value = serde_json::from_str(&contents)
+                .map_err(|e| ConfigError::new(file_path, "", &format!("Invalid JSON ({})", e)))?;
+            deserialize(file_path, value)
+        }
+    }
+}
+
+fn deserialize<'de, D, T>(file_path: &str, value: D) -> Result<T, ConfigError>
+where
+    D: serde::Deserializer<'de>,
+    D::Error: fmt::Display,
+    T: DeserializeOwned,
+{
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let key = e.path().to_string();
+        ConfigError::new(
+            file_path,
+            if key == "." { "" } else { &key },
+            &e.into_inner().to_string(),
+        )
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::component::block;
+    use crate::{gamemode, levels, themes};
+
+    // a copy of a shipped config file with one change, written to the temp directory
+    pub fn broken_copy(source: &str, name: &str, from: &str, to: &str) -> String {
+        let contents = std::fs::read_to_string(source).unwrap();
+        assert!(contents.contains(from));
+        write(name, &contents.replacen(from, to, 1))
+    }
+
+    pub fn write(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("hexpong-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        String::from(path.to_str().unwrap())
+    }
+
+    pub fn assert_error(error: Option<ConfigError>, path: &str, key: &str, reason: &str) {
+        let error = error.expect("Expected a config error");
+        assert_eq!(error.path, path);
+        assert_eq!(error.key, key);
+        assert_eq!(error.reason, reason);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn missing_key() {
+        let path = broken_copy(
+            "config/gamemodes/versus-ai.yaml",
+            "missing-key.yaml",
+            "      ball-speed: 0.72\n",
+            "",
+        );
+        assert_error(
+            gamemode::GameMode::new(&path, gamemode::Difficulty::Normal).err(),
+            &path,
+            "gamemode.difficulty.normal",
+            "missing field `ball-speed`",
+        );
+    }
+
+    #[test]
+    fn bad_enum_value() {
+        let path = broken_copy(
+            "config/levels/basic.yaml",
+            "bad-block.yaml",
+            "block: Basic }",
+            "block: Basic4 }",
+        );
+        assert_error(
+            levels::Level::new(path.clone()).err(),
+            &path,
+            "level.blocks[0].block",
+            "Invalid block type: Basic4",
+        );
+        let path = broken_copy(
+            "config/gamemodes/versus-ai.yaml",
+            "bad-controls.yaml",
+            "AI:normal",
+            "AI:insane",
+        );
+        assert_error(
+            gamemode::GameMode::new(&path, gamemode::Difficulty::Normal).err(),
+            &path,
+            "gamemode.difficulty.normal.controls.top.input",
+            "Invalid controls: AI:insane",
+        );
+    }
+
+    #[test]
+    fn bad_colour() {
+        let path = broken_copy(
+            "config/themes/base.yaml",
+            "bad-colour.yaml",
+            "\"303030\"",
+            "\"30G030\"",
+        );
+        assert_error(
+            themes::Theme::new(path.clone()).err(),
+            &path,
+            "theme.background",
+            "Unable to decode color: 30G030",
+        );
+    }
+
+    #[test]
+    fn zero_lives() {
+        let path = broken_copy(
+            "config/gamemodes/versus-ai.yaml",
+            "zero-lives.yaml",
+            "count: 3",
+            "count: 0",
+        );
+        assert_error(
+            gamemode::GameMode::new(&path, gamemode::Difficulty::Normal).err(),
+            &path,
+            "gamemode.lives.count",
+            "Expected a positive number",
+        );
+    }
+
+    #[test]
+    fn empty_level_list() {
+        let contents = std::fs::read_to_string("config/gamemodes/versus-ai.yaml").unwrap();
+        let levels = contents.find("  levels:").unwrap();
+        let path = write(
+            "no-levels.yaml",
+            &format!("{}  levels: []\n", &contents[..levels]),
+        );
+        assert_error(
+            gamemode::GameMode::new(&path, gamemode::Difficulty::Normal).err(),
+            &path,
+            "gamemode.levels",
+            "At least one level is required",
+        );
+    }
+
+    const TOML_THEME: &str = r#"[theme]
+background = "303030"
+wall = "A0A0A0"
+player1 = "00FFFF"
+player2 = "FF00FF"
+player3 = "FFFF00"
+blocks = [
+    { block = "Basic", color = "7FFF7F" },
+    { block = "Immortal", color = "7F7F7F" },
+    { block = "Basic2", color = "FF7F7F" },
+    { block = "Basic3", color = "7F7FFF" },
+    { block = "Explosive", color = "FF9F3F" },
+    { block = "Multiball", color = "FFFFFF" },
+]
+"#;
+
+    const JSON_LEVEL: &str = r#"{
+  "level": {
+    "name": "Pair",
+    "shape": "Hexagonal",
+    "block-size": 70.0,
+    "explosions": { "radius": 2 },
+    "blocks": [
+      { "q": 0, "r": 0, "block": "Basic" },
+      { "q": 1, "r": 0, "block": "Explosive" }
+    ]
+  }
+}
+"#;
+
+    #[test]
+    fn toml_file() {
+        let path = write("theme.toml", TOML_THEME);
+        let theme = themes::Theme::new(path.clone()).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(
+            theme.wall,
+            ggez::graphics::Color::from_rgb(0xA0, 0xA0, 0xA0)
+        );
+        assert_eq!(
+            theme.get_block_color(&block::BlockType::Immortal),
+            ggez::graphics::Color::from_rgb(0x7F, 0x7F, 0x7F)
+        );
+
+        let path = write(
+            "bad-theme.toml",
+            &TOML_THEME.replacen("\"7F7F7F\"", "\"7F7F7\"", 1),
+        );
+        assert_error(
+            themes::Theme::new(path.clone()).err(),
+            &path,
+            "theme.blocks[1].color",
+            "Unable to decode color: 7F7F7",
+        );
+    }
+
+    #[test]
+    fn json_file() {
+        let path = write("level.json", JSON_LEVEL);
+        let level = levels::Level::new(path.clone()).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(level.name, "Pair");
+        assert_eq!(level.blocks.len(), 2);
+        assert_eq!(level.explosions.radius, 2);
+
+        let path = write(
+            "bad-level.json",
+            &JSON_LEVEL.replacen("\"Explosive\"", "\"Explosion\"", 1),
+        );
+        assert_error(
+            levels::Level::new(path.clone()).err(),
+            &path,
+            "level.blocks[1].block",
+            "Invalid block type: Explosion",
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_reported_per_format() {
+        let path = write("broken.toml", "[theme\nbackground = 1");
+        let error = themes::Theme::new(path.clone()).err().unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(error.key, "");
+        assert!(error.reason.starts_with("Invalid TOML"));
+
+        let path = write("broken.json", "{ \"level\": ");
+        let error = levels::Level::new(path.clone()).err().unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(error.key, "");
+        assert!(error.reason.starts_with("Invalid JSON"));
+    }
+
+    #[test]
+    fn unknown_file_extension() {
+        let path = write("settings.ini", "gamemode = arcade");
+        assert_error(
+            load::<serde_yaml::Value>(&path).err(),
+            &path,
+            "",
+            "Unsupported file extension",
+        );
+    }
+}
@@ -0,0 +1,585 @@
+use crate::bindings;
+use crate::config::ConfigError;
+use crate::gamemode;
+use crate::replay;
+use crate::settings;
+use crate::simulation;
+use crate::themes;
+use crate::{AudibleComponent, InputState, VisualComponent};
+use ggez::audio::*;
+use ggez::event::{Axis, Button, GamepadId, KeyCode};
+use ggez::*;
+
+impl AudibleComponent for simulation::Event {
+    fn play_sound(&self, ctx: &mut Context) {
+        let sound = match self {
+            simulation::Event::BarHit => "/back_003.ogg",
+            simulation::Event::WallHit => "/impactMetal_medium_003.ogg",
+            simulation::Event::BlockHit { .. } => "/impactGlass_medium_000.ogg",
+            simulation::Event::BallHit => "/impactGlass_medium_001.ogg",
+            simulation::Event::Explosion { .. } => "/impactGlass_medium_004.ogg",
+            simulation::Event::MultiballSpawned => "/impactGlass_medium_002.ogg",
+            simulation::Event::BallLost { .. }
+            | simulation::Event::BallNudged
+            | simulation::Event::BallLaunched
+            | simulation::Event::LevelCompleted
+            | simulation::Event::LevelStarted
+            | simulation::Event::GameOver
+            | simulation::Event::Victory => return,
+        };
+        ggez::audio::Source::new(ctx, sound)
+            .unwrap()
+            .play_detached()
+            .unwrap();
+    }
+}
+
+pub struct GameState {
+    pub simulation: simulation::Simulation,
+    pub theme: themes::Theme,
+    pub bindings: bindings::Bindings,
+    // gamepad claimed by each player in the lobby
+    pub gamepads: Vec<Option<GamepadId>>,
+    // players whose gamepad got unplugged during the game
+    disconnected: Vec<usize>,
+    inputs: Vec<InputState>,
+    recording: Option<replay::Replay>,
+    playback: Option<replay::Playback>,
+    gamemode_path: String,
+    difficulty: gamemode::Difficulty,
+    start_level: usize,
+    theme_path: String,
+    nudge_message_ticks: u32,
+    explosions: Vec<Explosion>,
+}
+
+struct Explosion {
+    x: f32,
+    y: f32,
+    radius: f32,
+    ticks_left: u32,
+}
+
+const NUDGE_MESSAGE_TIME: f32 = 1.5;
+const EXPLOSION_TIME: f32 = 0.4;
+
+fn explosion_ticks() -> u32 {
+    (EXPLOSION_TIME * settings::TICK_RATE as f32) as u32
+}
+
+impl GameState {
+    pub fn new(
+        gamemode_path: &str,
+        difficulty: gamemode::Difficulty,
+        start_level: usize,
+        theme_path: &str,
+        seed: u64,
+    ) -> Result<Self, ConfigError> {
+        let mode = gamemode::GameMode::new(gamemode_path, difficulty)?;
+        GameState::with_mode(
+            mode,
+            gamemode_path,
+            difficulty,
+            start_level,
+            theme_path,
+            seed,
+        )
+    }
+
+    pub fn from_replay(replay: replay::Replay, theme_path: &str) -> Result<Self, ConfigError> {
+        let mode = gamemode::GameMode::new(&replay.gamemode, replay.difficulty)?;
+        let start_level = mode
+            .levels
+            .iter()
+            .position(|level| level.path == replay.level)
+            .ok_or_else(|| {
+                ConfigError::new(
+                    &replay.gamemode,
+                    "gamemode.levels",
+                    &format!("Replay level {} is not part of the gamemode", replay.level),
+                )
+            })?;
+        let mut state = GameState::with_mode(
+            mode,
+            &replay.gamemode,
+            replay.difficulty,
+            start_level,
+            theme_path,
+            replay.seed,
+        )?;
+        state.recording = None;
+        state.playback = Some(replay::Playback::new(replay));
+        Ok(state)
+    }
+
+    fn with_mode(
+        mode: gamemode::GameMode,
+        gamemode_path: &str,
+        difficulty: gamemode::Difficulty,
+        start_level: usize,
+        theme_path: &str,
+        seed: u64,
+    ) -> Result<Self, ConfigError> {
+        let bindings = bindings::Bindings::load_current()?;
+        if let Some(player) = mode
+            .players
+            .iter()
+            .find(|player| player.ai.is_none() && player.player.index() >= bindings.players.len())
+        {
+            return Err(ConfigError::new(
+                bindings::Bindings::current_file(),
+                "bindings.players",
+                &format!("Missing bindings for player {}", player.player.index() + 1),
+            ));
+        }
+        let inputs = vec![InputState::default(); mode.players.len()];
+        let recording = replay::Replay::new(
+            gamemode_path,
+            difficulty,
+            &mode.levels[start_level].path,
+            seed,
+        );
+        Ok(GameState {
+            simulation: simulation::Simulation::new(mode, start_level, seed),
+            theme: themes::Theme::new(String::from(theme_path))?,
+            bindings: bindings,
+            gamepads: vec![None; inputs.len()],
+            disconnected: Vec::new(),
+            inputs: inputs,
+            recording: Some(recording),
+            playback: None,
+            gamemode_path: String::from(gamemode_path),
+            difficulty: difficulty,
+            start_level: start_level,
+            theme_path: String::from(theme_path),
+            nudge_message_ticks: 0,
+            explosions: Vec::new(),
+        })
+    }
+
+    pub fn restart(&mut self) -> Result<(), ConfigError> {
+        let gamepads = self.gamepads.clone();
+        *self = GameState::new(
+            &self.gamemode_path,
+            self.difficulty,
+            self.start_level,
+            &self.theme_path,
+            rand::random(),
+        )?;
+        self.gamepads = gamepads;
+        Ok(())
+    }
+
+    pub fn save_recording(&mut self) -> Result<(), ConfigError> {
+        // the recording is taken, so that a game is saved (or fails to save) only once
+        if let Some(recording) = self.recording.take() {
+            std::fs::create_dir_all(settings::REPLAY_DIR)
+                .map_err(|error| ConfigError::new(settings::REPLAY_DIR, "", &error.to_string()))?;
+            recording.save(&format!(
+                "{}/replay-{}.yaml",
+                settings::REPLAY_DIR,
+                recording.seed
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.simulation.phase {
+            simulation::Phase::GameOver | simulation::Phase::Victory => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_pause_key(&self, keycode: KeyCode) -> bool {
+        self.bindings.pause.0 == keycode
+    }
+
+    pub fn reload_bindings(&mut self) {
+        // a binding file broken outside of the game keeps the current bindings
+        if let Ok(bindings) = bindings::Bindings::load_current() {
+            self.bindings = bindings;
+        }
+        self.release_inputs();
+    }
+
+    pub fn release_inputs(&mut self) {
+        if self.playback.is_some() {
+            return;
+        }
+        for input in self.inputs.iter_mut() {
+            *input = InputState::default();
+        }
+    }
+
+    pub fn update_input(&mut self, keycode: KeyCode, key_pressed: bool) {
+        if self.playback.is_some() {
+            return;
+        }
+        let binding = match self.bindings.find(keycode) {
+            Some(binding) => binding,
+            None => return,
+        };
+        let player = match binding.player {
+            Some(index) => self
+                .simulation
+                .players
+                .iter()
+                .position(|player| player.player.index() == index),
+            None => None,
+        };
+        if let Some(input) = player.and_then(|player| self.inputs.get_mut(player)) {
+            let value = if key_pressed { 1.0 } else { 0.0 };
+            match binding.action {
+                // the keyboard takes over from the mouse until it moves again
+                bindings::Action::Left => {
+                    input.left = value;
+                    input.target = None;
+                }
+                bindings::Action::Right => {
+                    input.right = value;
+                    input.target = None;
+                }
+                bindings::Action::Launch => input.launch = key_pressed,
+                bindings::Action::Pause => (),
+            }
+        }
+    }
+
+    pub fn check_gamepads(&mut self, ctx: &Context) -> bool {
+        // returns true when a claimed gamepad was unplugged since the last check
+        let mut unplugged = false;
+        for (index, gamepad) in self.gamepads.iter().enumerate() {
+            if let Some(id) = gamepad {
+                let connected = input::gamepad::gamepad(ctx, *id).is_connected();
+                let known = self.disconnected.contains(&index);
+                if !connected && !known {
+                    self.disconnected.push(index);
+                    unplugged = true;
+                } else if connected && known {
+                    self.disconnected.retain(|&player| player != index);
+                }
+            }
+        }
+        if unplugged {
+            self.release_inputs();
+        }
+        unplugged
+    }
+
+    fn gamepad_input(&mut self, id: GamepadId) -> Option<&mut InputState> {
+        if self.playback.is_some() {
+            return None;
+        }
+        let player = self
+            .gamepads
+            .iter()
+            .position(|gamepad| *gamepad == Some(id))?;
+        self.inputs.get_mut(player)
+    }
+
+    pub fn update_gamepad_button(&mut self, button: Button, id: GamepadId, pressed: bool) {
+        if let Some(input) = self.gamepad_input(id) {
+            let value = if pressed { 1.0 } else { 0.0 };
+            match button {
+                Button::DPadLeft => input.left = value,
+                Button::DPadRight => input.right = value,
+                Button::South => input.launch = pressed,
+                _ => return,
+            }
+            input.target = None;
+        }
+    }
+
+    pub fn update_gamepad_axis(&mut self, axis: Axis, value: f32, id: GamepadId) {
+        if axis != Axis::LeftStickX {
+            return;
+        }
+        if let Some(input) = self.gamepad_input(id) {
+            let value = ((value.abs() - settings::GAMEPAD_DEADZONE)
+                / (1.0 - settings::GAMEPAD_DEADZONE))
+                .max(0.0)
+                .min(1.0)
+                * value.signum();
+            input.left = (-value).max(0.0);
+            input.right = value.max(0.0);
+            input.target = None;
+        }
+    }
+
+    pub fn update_mouse(&mut self, x: f32, y: f32) {
+        if self.playback.is_some() {
+            return;
+        }
+        let mouse_player = match self.bindings.mouse_player {
+            Some(player) => player,
+            None => return,
+        };
+        let (x, y) = (
+            settings::pixel_to_unit(x - settings::ORIGIN.0),
+            settings::pixel_to_unit(y - settings::ORIGIN.1),
+        );
+        let side = gamemode::Side::from_position(x, y);
+        for (input, player) in self.inputs.iter_mut().zip(self.simulation.players.iter()) {
+            if player.player.index() != mouse_player {
+                continue;
+            }
+            // the bar on the side the cursor points at, any bar works as they share barpos
+            let bar = player
+                .bars
+                .iter()
+                .find(|bar| bar.side == side)
+                .or_else(|| player.bars.first());
+            if let Some(bar) = bar {
+                input.target = Some(bar.project(x, y));
+            }
+        }
+    }
+
+    pub fn update(&mut self, ctx: &mut Context, ticks: u32) {
+        for _ in 0..ticks {
+            if let Some(playback) = &mut self.playback {
+                playback.apply(self.simulation.tick, &mut self.inputs);
+            }
+            if let Some(recording) = &mut self.recording {
+                recording.record(self.simulation.tick, &self.inputs);
+            }
+            self.nudge_message_ticks = self.nudge_message_ticks.saturating_sub(1);
+            for explosion in self.explosions.iter_mut() {
+                explosion.ticks_left = explosion.ticks_left.saturating_sub(1);
+            }
+            self.explosions.retain(|explosion| explosion.ticks_left > 0);
+            for event in self.simulation.step(&self.inputs).iter() {
+                if let simulation::Event::BallNudged = event {
+                    self.nudge_message_ticks =
+                        (NUDGE_MESSAGE_TIME * settings::TICK_RATE as f32) as u32;
+                }
+                if let simulation::Event::Explosion { x, y, radius } = event {
+                    self.explosions.push(Explosion {
+                        x: *x,
+                        y: *y,
+                        radius: *radius,
+                        ticks_left: explosion_ticks(),
+                    });
+                }
+                event.play_sound(ctx);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, self.theme.background);
+        self.simulation.level.draw(ctx, &self.theme)?;
+        for wall in self.simulation.walls.iter_mut() {
+            wall.draw(ctx, &self.theme)?;
+        }
+        for player in self.simulation.players.iter_mut() {
+            player.draw(ctx, &self.theme)?;
+        }
+        for ball in self.simulation.balls.iter_mut() {
+            ball.draw(ctx, &self.theme)?;
+        }
+        for explosion in self.explosions.iter() {
+            // the blast grows to its full radius while fading out
+            let progress = 1.0 - explosion.ticks_left as f32 / explosion_ticks() as f32;
+            let circle = graphics::Mesh::new_circle(
+                ctx,
+                graphics::DrawMode::fill(),
+                mint::Point2 {
+                    x: settings::ORIGIN.0 + settings::unit_to_pixel(explosion.x),
+                    y: settings::ORIGIN.1 + settings::unit_to_pixel(explosion.y),
+                },
+                settings::unit_to_pixel(explosion.radius * (0.3 + 0.7 * progress)),
+                0.5,
+                [1.0, 0.6, 0.2, 0.8 * (1.0 - progress)].into(),
+            )?;
+            graphics::draw(ctx, &circle, graphics::DrawParam::default())?;
+        }
+        let fps = ggez::timer::fps(ctx) as f32;
+        let fps_text = ggez::graphics::Text::new(format!("FPS: {:.1}", fps));
+        graphics::draw(
+            ctx,
+            &fps_text,
+            ggez::graphics::DrawParam::from((
+                mint::Point2 { x: 10.0, y: 10.0 },
+                0.0,
+                mint::Point2 { x: 0.0, y: 0.0 },
+                mint::Vector2 { x: 1.0, y: 1.0 },
+                [(60.0 - fps) / 60.0, 1.0 - (60.0 - fps) / 60.0, 0.0, 1.0].into(),
+            )),
+        )?;
+        for (index, player) in self.simulation.players.iter().enumerate() {
+            let score_text = ggez::graphics::Text::new(if self.simulation.lives.shared {
+                format!("P{}: {}", player.player.index() + 1, player.score)
+            } else {
+                format!(
+                    "P{}: {} ({})",
+                    player.player.index() + 1,
+                    player.score,
+                    player.lives
+                )
+            });
+            graphics::draw(
+                ctx,
+                &score_text,
+                ggez::graphics::DrawParam::from((
+                    mint::Point2 {
+                        x: settings::SCREEN_SIZE.0 - 100.0,
+                        y: 10.0 + 20.0 * index as f32,
+                    },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    self.theme.get_player_color(&player.player),
+                )),
+            )?;
+        }
+        if self.simulation.lives.shared {
+            let lives_text =
+                ggez::graphics::Text::new(format!("Lives: {}", self.simulation.shared_lives));
+            graphics::draw(
+                ctx,
+                &lives_text,
+                ggez::graphics::DrawParam::from((
+                    mint::Point2 { x: 10.0, y: 30.0 },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    graphics::WHITE,
+                )),
+            )?;
+        }
+        if self.nudge_message_ticks > 0 {
+            let nudge_text = ggez::graphics::Text::new("Deadlock: ball nudged");
+            graphics::draw(
+                ctx,
+                &nudge_text,
+                ggez::graphics::DrawParam::from((
+                    mint::Point2 {
+                        x: 10.0,
+                        y: settings::SCREEN_SIZE.1 - 30.0,
+                    },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    [1.0, 0.8, 0.2, 1.0].into(),
+                )),
+            )?;
+        }
+        for (line, player) in self.disconnected.iter().enumerate() {
+            let disconnected_text = ggez::graphics::Text::new(format!(
+                "P{} gamepad disconnected",
+                self.simulation.players[*player].player.index() + 1
+            ));
+            graphics::draw(
+                ctx,
+                &disconnected_text,
+                ggez::graphics::DrawParam::from((
+                    mint::Point2 {
+                        x: 10.0,
+                        y: settings::SCREEN_SIZE.1 - 50.0 - 20.0 * line as f32,
+                    },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    [1.0, 0.4, 0.4, 1.0].into(),
+                )),
+            )?;
+        }
+        if self.simulation.launch_ticks > 0 && self.playback.is_none() {
+            if let Some(player) = self.bindings.players.first() {
+                draw_message(
+                    ctx,
+                    &format!("Press {} to launch", String::from(player.launch)),
+                )?;
+            }
+        }
+        if let simulation::Phase::Transition { .. } = self.simulation.phase {
+            draw_message(
+                ctx,
+                &format!("Level {} complete", self.simulation.level_number()),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn draw_message(ctx: &mut Context, message: &str) -> GameResult {
+    let text = ggez::graphics::Text::new(message);
+    graphics::draw(
+        ctx,
+        &text,
+        ggez::graphics::DrawParam::from((
+            mint::Point2 {
+                x: settings::ORIGIN.0 - 4.0 * message.len() as f32,
+                y: settings::ORIGIN.1 - 8.0,
+            },
+            0.0,
+            mint::Point2 { x: 0.0, y: 0.0 },
+            mint::Vector2 { x: 1.0, y: 1.0 },
+            graphics::WHITE,
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::shape::*;
+
+    #[test]
+    fn mouse_moves_the_bar_under_the_cursor() {
+        let mut state = GameState::new(
+            "config/gamemodes/arcade-multiplayer.yaml",
+            gamemode::Difficulty::Hard,
+            0,
+            settings::DEFAULT_THEME,
+            1,
+        )
+        .unwrap();
+        state.bindings.mouse_player = Some(1);
+        let player_index = state
+            .simulation
+            .players
+            .iter()
+            .position(|player| player.player.index() == 1)
+            .unwrap();
+        let sides: Vec<gamemode::Side> = state.simulation.players[player_index]
+            .bars
+            .iter()
+            .map(|bar| bar.side.clone())
+            .collect();
+        assert!(sides.len() > 1);
+        for side in sides.iter() {
+            // a point just inside the arena, a fifth of the side away from its center
+            let bar = state.simulation.players[player_index]
+                .bars
+                .iter()
+                .find(|bar| bar.side == *side)
+                .unwrap();
+            let phi = side.to_ang().to_radians();
+            let apothem = 0.8 * settings::UNIT_SIZE;
+            let axis = bar.axis();
+            let (x, y) = (
+                apothem * phi.cos() - 0.2 * settings::UNIT_SIZE * axis.dx,
+                apothem * phi.sin() - 0.2 * settings::UNIT_SIZE * axis.dy,
+            );
+            state.update_mouse(
+                settings::ORIGIN.0 + settings::unit_to_pixel(x),
+                settings::ORIGIN.1 + settings::unit_to_pixel(y),
+            );
+            assert!((state.inputs[player_index].target.unwrap() - 0.3).abs() < 1e-3);
+            for _ in 0..settings::TICK_RATE {
+                state.simulation.step(&state.inputs);
+            }
+            let bar = state.simulation.players[player_index]
+                .bars
+                .iter()
+                .find(|bar| bar.side == *side)
+                .unwrap();
+            let center = bar.shape.center();
+            let offset = (center.x - x) * axis.dx + (center.y - y) * axis.dy;
+            assert!(offset.abs() < 1.0, "{}: {}", side.to_str(), offset);
+        }
+    }
+}
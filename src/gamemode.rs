@@ -1,24 +1,39 @@
+use crate::component::block;
 use crate::component::controller;
 use crate::component::wall;
+use crate::config;
+use crate::config::ConfigError;
 use crate::levels;
-use std::fs::File;
-use std::io::Read;
-use yaml_rust::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum Difficulty {
     Easy,
     Normal,
     Hard,
 }
 
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
 pub enum Controls {
     Player1,
     Player2,
     Player3,
+    Ai(Difficulty),
     Wall,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Direction {
+    Normal,
+    Reversed,
+}
+
+#[derive(Clone, PartialEq)]
 pub enum Side {
     Bottom,
     BottomLeft,
@@ -28,134 +43,408 @@ pub enum Side {
     TopRight,
 }
 
+#[derive(Clone)]
+pub struct Points {
+    pub ball_lost: i32,
+    blocks: HashMap<block::BlockType, i32>,
+}
+
+impl Points {
+    pub fn get_block_points(&self, block_type: &block::BlockType) -> i32 {
+        *self.blocks.get(block_type).unwrap_or(&0)
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct Lives {
+    pub count: u32,
+    pub shared: bool,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct BarMovement {
+    // largest change of bar position per second, the side length being 1.0
+    pub speed: f32,
+    // change of speed per second while moving
+    pub acceleration: f32,
+    // change of speed per second after the input is released
+    pub friction: f32,
+}
+
+impl Default for BarMovement {
+    fn default() -> Self {
+        BarMovement {
+            speed: 1.8,
+            acceleration: 18.0,
+            friction: 18.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct English {
+    // degrees away from the bar normal
+    pub max_deflection: f32,
+    // degrees added when the ball hits the very end of the bar
+    pub offset_deflection: f32,
+    // fraction of the bar velocity passed on to the ball
+    pub velocity_transfer: f32,
+}
+
+impl Default for English {
+    fn default() -> Self {
+        English {
+            max_deflection: 60.0,
+            offset_deflection: 20.0,
+            velocity_transfer: 0.3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Deadlock {
+    // seconds without touching a bar or a destructible block
+    pub timeout: f32,
+    // bounces off the same spot that count as a trajectory cycle
+    pub cycle_repeats: usize,
+    // largest random change of direction, in degrees
+    pub nudge_angle: f32,
+}
+
+impl Default for Deadlock {
+    fn default() -> Self {
+        Deadlock {
+            timeout: 15.0,
+            cycle_repeats: 3,
+            nudge_angle: 10.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum GameOver {
+    AnyPlayer,
+    AllPlayers,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WinCondition {
+    ClearBlocks,
+    Points(i32),
+    Survive(f32),
+}
+
+#[derive(Deserialize)]
+struct GameModeFile {
+    gamemode: GameModeConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GameModeConfig {
+    pub difficulty: DifficultiesConfig,
+    pub lives: Lives,
+    pub game_over: GameOver,
+    pub win_conditions: Vec<WinConditionConfig>,
+    pub points: PointsConfig,
+    pub levels: Vec<HashMap<String, String>>,
+    #[serde(default)]
+    pub ball_collisions: bool,
+    #[serde(default)]
+    pub deadlock: Deadlock,
+}
+
+#[derive(Deserialize)]
+pub struct DifficultiesConfig {
+    pub easy: DifficultyConfig,
+    pub normal: DifficultyConfig,
+    pub hard: DifficultyConfig,
+}
+
+impl DifficultiesConfig {
+    pub fn get(&self, difficulty: Difficulty) -> &DifficultyConfig {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DifficultyConfig {
+    pub bar_size: f32,
+    pub ball_speed: f32,
+    #[serde(default)]
+    pub bar_movement: BarMovement,
+    #[serde(default)]
+    pub english: English,
+    pub controls: ControlsConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ControlsConfig {
+    pub bottom: SideConfig,
+    pub bottom_left: SideConfig,
+    pub bottom_right: SideConfig,
+    pub top: SideConfig,
+    pub top_left: SideConfig,
+    pub top_right: SideConfig,
+}
+
+impl ControlsConfig {
+    pub fn get(&self, side: &Side) -> &SideConfig {
+        match side {
+            Side::Bottom => &self.bottom,
+            Side::BottomLeft => &self.bottom_left,
+            Side::BottomRight => &self.bottom_right,
+            Side::Top => &self.top,
+            Side::TopLeft => &self.top_left,
+            Side::TopRight => &self.top_right,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SideConfig {
+    pub input: Controls,
+    pub direction: Option<Direction>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "condition", rename_all = "kebab-case")]
+pub enum WinConditionConfig {
+    ClearBlocks,
+    Points { value: i32 },
+    Survive { value: f32 },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PointsConfig {
+    pub ball_lost: i32,
+    pub blocks: Vec<BlockPointsConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct BlockPointsConfig {
+    pub block: block::BlockType,
+    pub points: i32,
+}
+
+#[derive(Clone)]
 pub struct GameMode {
     pub players: Vec<controller::Controller>,
     pub walls: Vec<wall::Wall>,
     pub levels: Vec<levels::Level>,
     pub ball_speed: f32,
+    pub ball_collisions: bool,
+    pub english: English,
+    pub deadlock: Deadlock,
+    pub points: Points,
+    pub lives: Lives,
+    pub game_over: GameOver,
+    pub win_conditions: Vec<WinCondition>,
 }
 
 impl GameMode {
-    pub fn new(file_path: &str, difficulty: Difficulty) -> Self {
-        let mut f = File::open(file_path).expect("Unable to open gamemode file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("Unable to read gamemode file");
-        let yaml = &YamlLoader::load_from_str(&contents).unwrap()[0]["gamemode"];
-
-        let bar_size = yaml["difficulty"][difficulty.to_str()]["bar-size"]
-            .as_f64()
-            .expect("Missing bar-size property") as f32;
-
-        let ball_speed = yaml["difficulty"][difficulty.to_str()]["ball-speed"]
-            .as_f64()
-            .expect("Missing ball-speed property") as f32;
-
-        let mut players = Vec::new();
+    pub fn new(file_path: &str, difficulty: Difficulty) -> Result<Self, ConfigError> {
+        let file: GameModeFile = config::load(file_path)?;
+        let gamemode = file.gamemode;
+        let settings = gamemode.difficulty.get(difficulty);
+        let key = format!("gamemode.difficulty.{}", difficulty.to_str());
+
         let mut walls = Vec::new();
-        let mut player1 = controller::Controller::new(bar_size, controller::Player::Player1);
-        let mut player2 = controller::Controller::new(bar_size, controller::Player::Player2);
-        let mut player3 = controller::Controller::new(bar_size, controller::Player::Player3);
+        let mut controllers = vec![
+            controller::Controller::new(
+                settings.bar_size,
+                settings.bar_movement,
+                controller::Player::Player1,
+            ),
+            controller::Controller::new(
+                settings.bar_size,
+                settings.bar_movement,
+                controller::Player::Player2,
+            ),
+            controller::Controller::new(
+                settings.bar_size,
+                settings.bar_movement,
+                controller::Player::Player3,
+            ),
+        ];
 
-        for side in [
+        let sides = [
             Side::Bottom,
             Side::BottomLeft,
             Side::BottomRight,
             Side::Top,
             Side::TopLeft,
             Side::TopRight,
-        ]
-        .iter()
-        {
-            let input = yaml["difficulty"][difficulty.to_str()]["controls"][side.to_str()]["input"]
-                .as_str()
-                .expect("Missing or invalid difficulty configuration");
-            match Controls::from_str(input) {
-                Controls::Player1 => {
-                    player1.bars.push(controller::Bar::new(
-                        side,
-                        bar_size,
-                        controller::Player::Player1,
-                        parse_direction(
-                            yaml["difficulty"][difficulty.to_str()]["controls"][side.to_str()]
-                                ["direction"]
-                                .as_str()
-                                .expect("Missing or invalid difficulty configuration"),
-                        ),
-                    ));
-                }
-                Controls::Player2 => {
-                    player2.bars.push(controller::Bar::new(
-                        side,
-                        bar_size,
-                        controller::Player::Player2,
-                        parse_direction(
-                            yaml["difficulty"][difficulty.to_str()]["controls"][side.to_str()]
-                                ["direction"]
-                                .as_str()
-                                .expect("Missing or invalid difficulty configuration"),
-                        ),
-                    ));
-                }
-                Controls::Player3 => {
-                    player3.bars.push(controller::Bar::new(
-                        side,
-                        bar_size,
-                        controller::Player::Player3,
-                        parse_direction(
-                            yaml["difficulty"][difficulty.to_str()]["controls"][side.to_str()]
-                                ["direction"]
-                                .as_str()
-                                .expect("Missing or invalid difficulty configuration"),
-                        ),
-                    ));
+        ];
+        // every AI side gets its own controller, using the players left over by humans
+        let mut free_players: Vec<controller::Player> = controllers
+            .iter()
+            .map(|controller| controller.player)
+            .filter(|player| {
+                !sides
+                    .iter()
+                    .any(|side| match settings.controls.get(side).input {
+                        Controls::Player1 => *player == controller::Player::Player1,
+                        Controls::Player2 => *player == controller::Player::Player2,
+                        Controls::Player3 => *player == controller::Player::Player3,
+                        Controls::Ai(_) | Controls::Wall => false,
+                    })
+            })
+            .collect();
+        free_players.reverse();
+
+        for side in sides.iter() {
+            let controls = settings.controls.get(side);
+            let player = match controls.input {
+                Controls::Player1 => controller::Player::Player1,
+                Controls::Player2 => controller::Player::Player2,
+                Controls::Player3 => controller::Player::Player3,
+                Controls::Ai(level) => {
+                    let player = free_players.pop().ok_or_else(|| {
+                        ConfigError::new(
+                            file_path,
+                            &format!("{}.controls.{}.input", key, side.to_str()),
+                            "No player left for an AI, at most three players are supported",
+                        )
+                    })?;
+                    controllers[player.index()].ai = Some(level);
+                    player
                 }
                 Controls::Wall => {
                     walls.push(wall::Wall::new(side));
+                    continue;
                 }
+            };
+            let direction = controls.direction.ok_or_else(|| {
+                ConfigError::new(
+                    file_path,
+                    &format!("{}.controls.{}.direction", key, side.to_str()),
+                    "Missing direction for a player controlled side",
+                )
+            })?;
+            controllers[player.index()].bars.push(controller::Bar::new(
+                side,
+                settings.bar_size,
+                player,
+                match direction {
+                    Direction::Normal => false,
+                    Direction::Reversed => true,
+                },
+            ));
+        }
+        let players: Vec<controller::Controller> = controllers
+            .into_iter()
+            .filter(|controller| !controller.bars.is_empty())
+            .collect();
+        if players.is_empty() {
+            return Err(ConfigError::new(
+                file_path,
+                &format!("{}.controls", key),
+                "No side is controlled by a player",
+            ));
+        }
+        if gamemode.lives.count == 0 {
+            return Err(ConfigError::new(
+                file_path,
+                "gamemode.lives.count",
+                "Expected a positive number",
+            ));
+        }
+
+        let mut levels = Vec::new();
+        for level in gamemode.levels.iter() {
+            for level_path in level.values() {
+                levels.push(levels::Level::new(level_path.clone())?);
             }
         }
-        players.push(player1);
+        if levels.is_empty() {
+            return Err(ConfigError::new(
+                file_path,
+                "gamemode.levels",
+                "At least one level is required",
+            ));
+        }
 
-        GameMode {
+        Ok(GameMode {
             players: players,
             walls: walls,
-            ball_speed: ball_speed,
-            levels: Vec::new(),
-        }
+            ball_speed: settings.ball_speed,
+            ball_collisions: gamemode.ball_collisions,
+            english: settings.english,
+            deadlock: gamemode.deadlock,
+            levels: levels,
+            points: Points {
+                ball_lost: gamemode.points.ball_lost,
+                blocks: gamemode
+                    .points
+                    .blocks
+                    .iter()
+                    .map(|entry| (entry.block, entry.points))
+                    .collect(),
+            },
+            lives: gamemode.lives,
+            game_over: gamemode.game_over,
+            win_conditions: gamemode
+                .win_conditions
+                .iter()
+                .map(|condition| match condition {
+                    WinConditionConfig::ClearBlocks => WinCondition::ClearBlocks,
+                    WinConditionConfig::Points { value } => WinCondition::Points(*value),
+                    WinConditionConfig::Survive { value } => WinCondition::Survive(*value),
+                })
+                .collect(),
+        })
     }
 }
 
 impl Controls {
-    pub fn from_str(input: &str) -> Controls {
-        match &input.to_lowercase()[..] {
-            "player1" => Controls::Player1,
-            "player2" => Controls::Player2,
-            "player3" => Controls::Player3,
-            "wall" => Controls::Wall,
-            _ => panic!("Invalid controls"),
+    pub fn from_str(input: &str) -> Option<Controls> {
+        let input = input.to_lowercase();
+        if let Some(level) = input.strip_prefix("ai:") {
+            return Difficulty::from_str(level).map(Controls::Ai);
+        }
+        match &input[..] {
+            "player1" => Some(Controls::Player1),
+            "player2" => Some(Controls::Player2),
+            "player3" => Some(Controls::Player3),
+            "ai" => Some(Controls::Ai(Difficulty::Normal)),
+            "wall" => Some(Controls::Wall),
+            _ => None,
         }
     }
 }
 
-fn parse_direction(input: &str) -> bool {
-    match &input.to_lowercase()[..] {
-        "normal" => false,
-        "reversed" => true,
-        _ => panic!("Invalid direction"),
+impl Direction {
+    pub fn from_str(input: &str) -> Option<Direction> {
+        match &input.to_lowercase()[..] {
+            "normal" => Some(Direction::Normal),
+            "reversed" => Some(Direction::Reversed),
+            _ => None,
+        }
     }
 }
 
 impl Side {
-    pub fn from_str(input: &str) -> Side {
+    pub fn from_str(input: &str) -> Option<Side> {
         match &input.to_lowercase()[..] {
-            "bottom" => Side::Bottom,
-            "bottom-left)" => Side::BottomLeft,
-            "bottom-right" => Side::BottomRight,
-            "top" => Side::Top,
-            "top-left)" => Side::TopLeft,
-            "top-right" => Side::TopRight,
-            _ => panic!("Invalid side"),
+            "bottom" => Some(Side::Bottom),
+            "bottom-left" => Some(Side::BottomLeft),
+            "bottom-right" => Some(Side::BottomRight),
+            "top" => Some(Side::Top),
+            "top-left" => Some(Side::TopLeft),
+            "top-right" => Some(Side::TopRight),
+            _ => None,
         }
     }
     pub fn to_str(&self) -> &str {
@@ -168,25 +457,59 @@ impl Side {
             Side::TopRight => "top-right",
         }
     }
+    pub fn from_position(x: f32, y: f32) -> Side {
+        let phi = y.atan2(x).to_degrees();
+        let mut closest = Side::Bottom;
+        let mut closest_dist = f32::MAX;
+        for side in [
+            Side::Bottom,
+            Side::BottomLeft,
+            Side::BottomRight,
+            Side::Top,
+            Side::TopLeft,
+            Side::TopRight,
+        ]
+        .iter()
+        {
+            let dist = (phi - side.to_ang()).rem_euclid(360.0);
+            let dist = dist.min(360.0 - dist);
+            if dist < closest_dist {
+                closest = side.clone();
+                closest_dist = dist;
+            }
+        }
+        closest
+    }
     pub fn to_ang(&self) -> f32 {
+        // direction of the side center as seen from the arena center, y pointing down
         match self {
-            Side::Bottom => 60.0,
-            Side::BottomLeft => 120.0,
-            Side::BottomRight => 0.0,
-            Side::Top => 240.0,
-            Side::TopLeft => 180.0,
-            Side::TopRight => 300.0,
+            Side::Bottom => 90.0,
+            Side::BottomLeft => 150.0,
+            Side::BottomRight => 30.0,
+            Side::Top => 270.0,
+            Side::TopLeft => 210.0,
+            Side::TopRight => 330.0,
+        }
+    }
+}
+
+impl GameOver {
+    pub fn from_str(input: &str) -> Option<GameOver> {
+        match &input.to_lowercase()[..] {
+            "any-player" => Some(GameOver::AnyPlayer),
+            "all-players" => Some(GameOver::AllPlayers),
+            _ => None,
         }
     }
 }
 
 impl Difficulty {
-    pub fn from_str(input: &str) -> Difficulty {
+    pub fn from_str(input: &str) -> Option<Difficulty> {
         match &input.to_lowercase()[..] {
-            "easy" => Difficulty::Easy,
-            "normal" => Difficulty::Normal,
-            "hard" => Difficulty::Hard,
-            _ => panic!("Invalid difficulty"),
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
         }
     }
     pub fn to_str(&self) -> &str {
@@ -197,3 +520,37 @@ impl Difficulty {
         }
     }
 }
+
+impl TryFrom<String> for Controls {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Controls::from_str(&input).ok_or_else(|| format!("Invalid controls: {}", input))
+    }
+}
+
+impl TryFrom<String> for Direction {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Direction::from_str(&input).ok_or_else(|| format!("Invalid direction: {}", input))
+    }
+}
+
+impl TryFrom<String> for GameOver {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        GameOver::from_str(&input).ok_or_else(|| format!("Invalid game-over condition: {}", input))
+    }
+}
+
+impl TryFrom<String> for Difficulty {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Difficulty::from_str(&input).ok_or_else(|| format!("Invalid difficulty: {}", input))
+    }
+}
+
+impl From<Difficulty> for String {
+    fn from(difficulty: Difficulty) -> String {
+        String::from(difficulty.to_str())
+    }
+}
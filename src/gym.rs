@@ -0,0 +1,234 @@
+use crate::component::block;
+use crate::config::ConfigError;
+use crate::gamemode;
+use crate::settings;
+use crate::simulation;
+use crate::InputState;
+use geometry::shape::*;
+use std::collections::HashSet;
+
+// positions and velocities are divided by the unit size, so the arena spans -1.0 to 1.0
+pub struct BallObservation {
+    pub x: f32,
+    pub y: f32,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+pub struct Observation {
+    pub balls: Vec<BallObservation>,
+    // barpos of every player, in the order of the actions
+    pub bars: Vec<f32>,
+    // one entry per block the level started with, true while the block is standing
+    pub blocks: Vec<bool>,
+}
+
+pub struct Environment {
+    pub simulation: simulation::Simulation,
+    mode: gamemode::GameMode,
+    start_level: usize,
+    // episodes are cut off after this many ticks, zero for no limit
+    max_ticks: u64,
+    level_index: usize,
+    cells: Vec<block::GridIndex>,
+    scores: Vec<i32>,
+}
+
+impl Environment {
+    pub fn new(
+        gamemode_path: &str,
+        difficulty: gamemode::Difficulty,
+        start_level: usize,
+        max_ticks: u64,
+    ) -> Result<Self, ConfigError> {
+        // the gamemode is only read once, every episode starts from a copy
+        let mode = gamemode::GameMode::new(gamemode_path, difficulty)?;
+        if start_level >= mode.levels.len() {
+            return Err(ConfigError::new(
+                gamemode_path,
+                "gamemode.levels",
+                &format!("Level {} is not part of the gamemode", start_level + 1),
+            ));
+        }
+        let simulation = simulation::Simulation::new(mode.clone(), start_level, 0);
+        let mut environment = Environment {
+            simulation: simulation,
+            mode: mode,
+            start_level: start_level,
+            max_ticks: max_ticks,
+            level_index: start_level,
+            cells: Vec::new(),
+            scores: Vec::new(),
+        };
+        environment.reset(0);
+        Ok(environment)
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.simulation.players.len()
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.simulation = simulation::Simulation::new(self.mode.clone(), self.start_level, seed);
+        self.level_index = self.simulation.level_index;
+        self.cells = self.level_cells();
+        self.scores = self.current_scores();
+        self.observe()
+    }
+
+    pub fn step(&mut self, actions: &[InputState]) -> (Observation, Vec<f32>, bool) {
+        self.simulation.step(actions);
+        if self.simulation.level_index != self.level_index {
+            self.level_index = self.simulation.level_index;
+            self.cells = self.level_cells();
+        }
+        // rewards are the score changes, made of block points and lost ball penalties
+        let scores = self.current_scores();
+        let rewards = scores
+            .iter()
+            .zip(self.scores.iter())
+            .map(|(score, previous)| (score - previous) as f32)
+            .collect();
+        self.scores = scores;
+        let done = match self.simulation.phase {
+            simulation::Phase::GameOver | simulation::Phase::Victory => true,
+            _ => self.max_ticks > 0 && self.simulation.tick >= self.max_ticks,
+        };
+        (self.observe(), rewards, done)
+    }
+
+    fn level_cells(&self) -> Vec<block::GridIndex> {
+        self.simulation
+            .level
+            .blocks
+            .iter()
+            .map(|block| block.index)
+            .collect()
+    }
+
+    fn current_scores(&self) -> Vec<i32> {
+        self.simulation
+            .players
+            .iter()
+            .map(|player| player.score)
+            .collect()
+    }
+
+    fn observe(&self) -> Observation {
+        let standing: HashSet<block::GridIndex> = self
+            .simulation
+            .level
+            .blocks
+            .iter()
+            .map(|block| block.index)
+            .collect();
+        Observation {
+            balls: self
+                .simulation
+                .balls
+                .iter()
+                .map(|ball| BallObservation {
+                    x: ball.shape.center().x / settings::UNIT_SIZE,
+                    y: ball.shape.center().y / settings::UNIT_SIZE,
+                    dx: ball.velocity.dx / settings::UNIT_SIZE,
+                    dy: ball.velocity.dy / settings::UNIT_SIZE,
+                })
+                .collect(),
+            bars: self
+                .simulation
+                .players
+                .iter()
+                .map(|player| player.barpos)
+                .collect(),
+            blocks: self
+                .cells
+                .iter()
+                .map(|cell| standing.contains(cell))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment(max_ticks: u64) -> Environment {
+        Environment::new(
+            "config/gamemodes/versus-ai.yaml",
+            gamemode::Difficulty::Normal,
+            0,
+            max_ticks,
+        )
+        .unwrap()
+    }
+
+    fn flatten(observation: &Observation) -> Vec<f32> {
+        let mut values: Vec<f32> = observation
+            .balls
+            .iter()
+            .flat_map(|ball| vec![ball.x, ball.y, ball.dx, ball.dy])
+            .collect();
+        values.extend(observation.bars.iter());
+        values.extend(
+            observation
+                .blocks
+                .iter()
+                .map(|&standing| if standing { 1.0 } else { 0.0 }),
+        );
+        values
+    }
+
+    fn episode(environment: &mut Environment, seed: u64) -> Vec<(Vec<f32>, Vec<f32>)> {
+        let mut steps = vec![(flatten(&environment.reset(seed)), Vec::new())];
+        for tick in 0..600 {
+            // swinging from side to side, launching right away
+            let action = InputState {
+                left: if tick / 40 % 2 == 0 { 1.0 } else { 0.0 },
+                right: if tick / 40 % 2 == 1 { 1.0 } else { 0.0 },
+                launch: true,
+                target: None,
+            };
+            let actions = vec![action; environment.player_count()];
+            let (observation, rewards, _) = environment.step(&actions);
+            steps.push((flatten(&observation), rewards));
+        }
+        steps
+    }
+
+    #[test]
+    fn same_seed_and_actions_give_the_same_episode() {
+        let mut environment = environment(0);
+        let first = episode(&mut environment, 9);
+        let second = episode(&mut environment, 9);
+        assert!(first == second);
+        // the balls moved, so the comparison covered more than the starting position
+        assert!(first[0].0 != first[first.len() - 1].0);
+    }
+
+    #[test]
+    fn episode_is_done_on_game_over() {
+        let mut environment = environment(0);
+        environment.reset(3);
+        let (_, _, done) = environment.step(&[]);
+        assert!(!done);
+        for player in environment.simulation.players.iter_mut() {
+            if player.ai.is_none() {
+                player.lives = 0;
+            }
+        }
+        let (_, _, done) = environment.step(&[]);
+        assert!(done);
+        assert!(environment.simulation.phase == simulation::Phase::GameOver);
+    }
+
+    #[test]
+    fn episode_is_cut_off_after_the_tick_limit() {
+        let mut environment = environment(10);
+        environment.reset(3);
+        for _ in 0..9 {
+            assert!(!environment.step(&[]).2);
+        }
+        assert!(environment.step(&[]).2);
+    }
+}
@@ -1,77 +1,323 @@
 use crate::component::block;
+use crate::config;
+use crate::config::ConfigError;
 use crate::themes;
 use crate::VisualComponent;
 use ggez::*;
-use std::fs::File;
-use std::io::Read;
-use yaml_rust::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "String")]
 pub enum LevelShape {
     Hexagonal,
 }
 
 impl LevelShape {
-    fn from_str(input: &str) -> LevelShape {
+    fn from_str(input: &str) -> Option<LevelShape> {
         match input {
-            "Hexagonal" => LevelShape::Hexagonal,
-            _ => panic!("Invalid level shape"),
+            "Hexagonal" => Some(LevelShape::Hexagonal),
+            _ => None,
         }
     }
 }
 
+impl TryFrom<String> for LevelShape {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        LevelShape::from_str(&input).ok_or_else(|| format!("Invalid level shape: {}", input))
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub enum MultiballSpawn {
+    Block,
+    Bar,
+}
+
+impl MultiballSpawn {
+    fn from_str(input: &str) -> Option<MultiballSpawn> {
+        match &input.to_lowercase()[..] {
+            "block" => Some(MultiballSpawn::Block),
+            "bar" => Some(MultiballSpawn::Bar),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<String> for MultiballSpawn {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        MultiballSpawn::from_str(&input)
+            .ok_or_else(|| format!("Invalid multiball spawn: {}", input))
+    }
+}
+
+#[derive(Deserialize)]
+struct LevelFile {
+    level: LevelConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LevelConfig {
+    pub name: String,
+    pub shape: LevelShape,
+    pub block_size: f32,
+    #[serde(default)]
+    pub explosions: Explosions,
+    #[serde(default)]
+    pub multiball: Multiball,
+    pub blocks: Vec<BlockConfig>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Explosions {
+    // blast radius of explosive blocks, in grid cells
+    pub radius: i32,
+    pub destroy_immortal: bool,
+}
+
+impl Default for Explosions {
+    fn default() -> Self {
+        Explosions {
+            radius: 1,
+            destroy_immortal: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Multiball {
+    // extra balls spawned by each multiball block
+    pub count: u32,
+    // at the block, or at the bar of the ball owner that last deflected it
+    pub spawn: MultiballSpawn,
+}
+
+impl Default for Multiball {
+    fn default() -> Self {
+        Multiball {
+            count: 2,
+            spawn: MultiballSpawn::Block,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BlockConfig {
+    pub q: i32,
+    pub r: i32,
+    pub block: block::BlockType,
+}
+
+#[derive(Clone)]
 pub struct Level {
+    pub path: String,
     pub name: String,
     pub shape: LevelShape,
     pub block_size: f32,
+    pub explosions: Explosions,
+    pub multiball: Multiball,
     pub blocks: Vec<block::Block>,
+    grid: HashMap<block::GridIndex, Vec<usize>>,
 }
 
 impl Level {
-    pub fn new(file_path: String) -> Self {
-        let mut f = File::open(file_path).expect("Unable to open level file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("Unable to read level file");
-        let yaml = &YamlLoader::load_from_str(&contents).unwrap()[0]["level"];
-        let block_size = yaml["block-size"]
-            .as_f64()
-            .expect("Missing property: block-size") as f32;
-        Level {
-            name: String::from(yaml["name"].as_str().expect("Missing property: name")),
-            shape: LevelShape::from_str(yaml["shape"].as_str().expect("Missing property: shape")),
+    pub fn new(file_path: String) -> Result<Self, ConfigError> {
+        let file: LevelFile = config::load(&file_path)?;
+        let level = file.level;
+        if level.block_size <= 0.0 {
+            return Err(ConfigError::new(
+                &file_path,
+                "level.block-size",
+                "Expected a positive number",
+            ));
+        }
+        if level.explosions.radius < 1 {
+            return Err(ConfigError::new(
+                &file_path,
+                "level.explosions.radius",
+                "Expected a positive number",
+            ));
+        }
+        let blocks = level
+            .blocks
+            .iter()
+            .map(|entry| {
+                block::Block::new(
+                    block::GridIndex {
+                        q: entry.q,
+                        r: entry.r,
+                    },
+                    level.block_size,
+                    entry.block,
+                )
+            })
+            .collect();
+        Ok(Level::from_blocks(
+            file_path,
+            level.name,
+            level.shape,
+            level.block_size,
+            level.explosions,
+            level.multiball,
+            blocks,
+        ))
+    }
+    pub fn from_blocks(
+        path: String,
+        name: String,
+        shape: LevelShape,
+        block_size: f32,
+        explosions: Explosions,
+        multiball: Multiball,
+        blocks: Vec<block::Block>,
+    ) -> Self {
+        let mut level = Level {
+            path: path,
+            name: name,
+            shape: shape,
             block_size: block_size,
-            blocks: parse_blocks(yaml, block_size),
+            explosions: explosions,
+            multiball: multiball,
+            blocks: blocks,
+            grid: HashMap::new(),
+        };
+        level.rebuild_grid();
+        level
+    }
+    fn rebuild_grid(&mut self) {
+        self.grid.clear();
+        for (block_index, block) in self.blocks.iter().enumerate() {
+            self.grid
+                .entry(block.index)
+                .or_insert_with(Vec::new)
+                .push(block_index);
+        }
+    }
+    pub fn blocks_near(&self, x: f32, y: f32, reach: f32) -> Vec<usize> {
+        // a block touching the reach circle has its center within reach plus one block radius,
+        // and neighbouring cell centers are at least 1.5 block radii apart
+        let cell = block::GridIndex::from_unit(x, y, self.block_size);
+        let distance = ((reach + 2.0 * self.block_size) / (1.5 * self.block_size)).ceil() as i32;
+        let mut block_indices = Vec::new();
+        for index in cell.range(distance).iter() {
+            if let Some(indices) = self.grid.get(index) {
+                block_indices.extend(indices.iter());
+            }
+        }
+        // same order as a linear scan, so that ties between impacts resolve the same way
+        block_indices.sort();
+        block_indices
+    }
+    pub fn blocks_in_range(&self, center: block::GridIndex, distance: i32) -> Vec<usize> {
+        let mut block_indices = Vec::new();
+        for index in center.range(distance).iter() {
+            if let Some(indices) = self.grid.get(index) {
+                block_indices.extend(indices.iter());
+            }
         }
+        block_indices.sort();
+        block_indices
     }
-    pub fn draw(&self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
-        for hexagon in self.blocks.iter() {
+    pub fn remove_blocks(&mut self, block_indices: &[usize]) {
+        if block_indices.is_empty() {
+            return;
+        }
+        let mut block_indices = block_indices.to_vec();
+        block_indices.sort();
+        block_indices.dedup();
+        // removed from the back, so that the remaining indices stay valid
+        for &block_index in block_indices.iter().rev() {
+            self.blocks.remove(block_index);
+        }
+        self.rebuild_grid();
+    }
+    pub fn is_completed(&self) -> bool {
+        self.blocks
+            .iter()
+            .all(|block| block.block_type == block::BlockType::Immortal)
+    }
+    pub fn draw(&mut self, ctx: &mut Context, theme: &themes::Theme) -> GameResult {
+        for hexagon in self.blocks.iter_mut() {
             hexagon.draw(ctx, theme)?;
         }
         Ok(())
     }
 }
 
-fn parse_blocks(yaml: &yaml_rust::Yaml, block_size: f32) -> Vec<block::Block> {
-    let mut blocks = Vec::new();
-    if !yaml["blocks"].is_array() {
-        panic! {"Invalid property: blocks"}
-    }
-    for node in yaml["blocks"].as_vec().unwrap() {
-        let index = block::GridIndex {
-            q: node["q"].as_i64().expect("Missing property: block index") as i32,
-            r: node["r"].as_i64().expect("Missing property: block index") as i32,
-        };
-        let point = index.to_unit(block_size);
-        blocks.push(block::Block::new(
-            point.x,
-            point.y,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings;
+    use geometry::shape::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn filled_level(block_size: f32) -> Level {
+        let blocks = block::GridIndex { q: 0, r: 0 }
+            .range(8)
+            .into_iter()
+            .map(|index| block::Block::new(index, block_size, block::BlockType::Basic))
+            .collect();
+        Level::from_blocks(
+            String::new(),
+            String::from("Filled"),
+            LevelShape::Hexagonal,
             block_size,
-            block::BlockType::from_str(
-                node["block"]
-                    .as_str()
-                    .expect("Missing property: block type"),
-            ),
-        ));
-    }
-    blocks
+            Explosions::default(),
+            Multiball::default(),
+            blocks,
+        )
+    }
+
+    #[test]
+    fn blocks_near_finds_every_block_a_linear_scan_would() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for &block_size in [35.0, 70.0, 120.0].iter() {
+            let level = filled_level(block_size);
+            for _ in 0..500 {
+                let x = rng.gen_range(-settings::UNIT_SIZE, settings::UNIT_SIZE);
+                let y = rng.gen_range(-settings::UNIT_SIZE, settings::UNIT_SIZE);
+                let reach = rng.gen_range(0.0, 400.0);
+                let near = level.blocks_near(x, y, reach);
+                // every block whose bounding circle overlaps the reach circle
+                for (index, block) in level.blocks.iter().enumerate() {
+                    let center = block.shape.center();
+                    let distance = ((center.x - x).powi(2) + (center.y - y).powi(2)).sqrt();
+                    if distance <= reach + block_size {
+                        assert!(
+                            near.contains(&index),
+                            "block {} missed at ({}, {}) with reach {}",
+                            index,
+                            x,
+                            y,
+                            reach
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_in_range_counts_hexagonal_distance() {
+        let level = filled_level(70.0);
+        let center = block::GridIndex { q: 2, r: -1 };
+        let mut expected: Vec<usize> = level
+            .blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.index.distance(&center) <= 2)
+            .map(|(index, _)| index)
+            .collect();
+        expected.sort();
+        assert_eq!(expected.len(), 19);
+        assert_eq!(level.blocks_in_range(center, 2), expected);
+    }
 }
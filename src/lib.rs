@@ -0,0 +1,56 @@
+use geometry::base::*;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+
+pub mod bindings;
+pub mod brain;
+pub mod component;
+pub mod config;
+mod converter;
+pub mod game;
+pub mod gamemode;
+pub mod gym;
+pub mod levels;
+pub mod replay;
+pub mod scene;
+pub mod settings;
+pub mod simulation;
+pub mod sweep;
+pub mod themes;
+
+pub trait AudibleComponent {
+    fn play_sound(&self, ctx: &mut Context);
+}
+
+pub trait PhysicalComponent {
+    fn collision(&self, ball: &component::ball::Ball, motion: Vector) -> Option<sweep::Impact>;
+    fn update(&mut self);
+}
+
+pub trait VisualComponent {
+    fn draw(&mut self, ctx: &mut Context, theme: &themes::Theme) -> GameResult;
+    fn create_mesh(&mut self, ctx: &mut Context) -> Option<graphics::Mesh>;
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct InputState {
+    // how strongly each direction is pressed, from 0.0 to 1.0
+    pub left: f32,
+    pub right: f32,
+    #[serde(default)]
+    pub launch: bool,
+    // barpos to move towards, set by pointing inputs such as the mouse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<f32>,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState {
+            left: 0.0,
+            right: 0.0,
+            launch: false,
+            target: None,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::config;
+use crate::config::ConfigError;
+use crate::gamemode;
+use crate::InputState;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+pub struct InputRecord {
+    pub tick: u64,
+    pub player: usize,
+    #[serde(flatten)]
+    pub input: InputState,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Replay {
+    pub gamemode: String,
+    pub difficulty: gamemode::Difficulty,
+    pub level: String,
+    pub seed: u64,
+    pub inputs: Vec<InputRecord>,
+    #[serde(skip)]
+    last_inputs: Vec<InputState>,
+}
+
+#[derive(Deserialize)]
+struct ReplayFile {
+    replay: Replay,
+}
+
+#[derive(Serialize)]
+struct ReplayFileRef<'a> {
+    replay: &'a Replay,
+}
+
+impl Replay {
+    pub fn new(gamemode: &str, difficulty: gamemode::Difficulty, level: &str, seed: u64) -> Self {
+        Replay {
+            gamemode: String::from(gamemode),
+            difficulty: difficulty,
+            level: String::from(level),
+            seed: seed,
+            inputs: Vec::new(),
+            last_inputs: Vec::new(),
+        }
+    }
+    pub fn load(file_path: &str) -> Result<Self, ConfigError> {
+        let file: ReplayFile = config::load(file_path)?;
+        Ok(file.replay)
+    }
+    pub fn save(&self, file_path: &str) -> Result<(), ConfigError> {
+        let contents = serde_yaml::to_string(&ReplayFileRef { replay: self })
+            .map_err(|error| ConfigError::new(file_path, "", &error.to_string()))?;
+        std::fs::write(file_path, contents)
+            .map_err(|error| ConfigError::new(file_path, "", &error.to_string()))
+    }
+    pub fn record(&mut self, tick: u64, inputs: &[InputState]) {
+        self.last_inputs.resize(inputs.len(), InputState::default());
+        for (player, input) in inputs.iter().enumerate() {
+            if *input != self.last_inputs[player] {
+                self.inputs.push(InputRecord {
+                    tick: tick,
+                    player: player,
+                    input: *input,
+                });
+                self.last_inputs[player] = *input;
+            }
+        }
+    }
+}
+
+pub struct Playback {
+    replay: Replay,
+    cursor: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback {
+            replay: replay,
+            cursor: 0,
+        }
+    }
+    pub fn apply(&mut self, tick: u64, inputs: &mut [InputState]) {
+        while let Some(record) = self.replay.inputs.get(self.cursor) {
+            if record.tick > tick {
+                break;
+            }
+            if let Some(input) = inputs.get_mut(record.player) {
+                *input = record.input;
+            }
+            self.cursor += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let mut replay = Replay::new(
+            "config/gamemodes/versus-ai.yaml",
+            gamemode::Difficulty::Normal,
+            "config/levels/basic.yaml",
+            42,
+        );
+        let mut input = InputState::default();
+        replay.record(0, &[input]);
+        input.left = 1.0;
+        replay.record(5, &[input]);
+        replay
+    }
+
+    #[test]
+    fn saved_replay_loads_back() {
+        let path = std::env::temp_dir().join(format!("hexpong-{}-replay.yaml", std::process::id()));
+        let path = path.to_str().unwrap();
+        replay().save(path).unwrap();
+        let loaded = Replay::load(path).unwrap();
+        std::fs::remove_file(path).ok();
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.inputs.len(), 1);
+        assert_eq!(loaded.inputs[0].tick, 5);
+        assert_eq!(loaded.inputs[0].input.left, 1.0);
+    }
+
+    #[test]
+    fn unwritable_replay_file() {
+        let path = std::env::temp_dir().join(format!("hexpong-{}-missing", std::process::id()));
+        let path = String::from(path.join("replay.yaml").to_str().unwrap());
+        let error = replay().save(&path).err();
+        assert_eq!(error.expect("Expected a config error").path, path);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::config::ConfigError;
+use crate::scene::{menu, Scene, Transition};
+use ggez::event::{KeyCode, MouseButton};
+use ggez::*;
+
+pub struct ErrorMessage {
+    menu: menu::Menu,
+    lines: Vec<String>,
+}
+
+impl ErrorMessage {
+    pub fn new(error: ConfigError) -> Self {
+        let mut lines = vec![format!("File: {}", error.path)];
+        if !error.key.is_empty() {
+            lines.push(format!("Key: {}", error.key));
+        }
+        lines.push(error.reason);
+        ErrorMessage {
+            menu: menu::Menu::new(
+                vec![String::from("Back"), String::from("Quit")],
+                100.0,
+                350.0,
+            ),
+            lines: lines,
+        }
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        match index {
+            0 => Transition::Pop,
+            _ => Transition::Quit,
+        }
+    }
+}
+
+impl Scene for ErrorMessage {
+    fn update(&mut self, _ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, graphics::BLACK);
+        menu::draw_title(ctx, "Unable to load configuration", 100.0, 120.0)?;
+        for (index, line) in self.lines.iter().enumerate() {
+            graphics::draw(
+                ctx,
+                &graphics::Text::new(line.as_str()),
+                graphics::DrawParam::from((
+                    mint::Point2 {
+                        x: 100.0,
+                        y: 200.0 + 24.0 * index as f32,
+                    },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    graphics::Color::from_rgb(255, 120, 120),
+                )),
+            )?;
+        }
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if keycode == KeyCode::Escape {
+            return Transition::Pop;
+        }
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.menu.mouse_motion_event(x, y);
+    }
+}
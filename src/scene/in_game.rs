@@ -0,0 +1,93 @@
+use crate::config::ConfigError;
+use crate::game;
+use crate::scene::{error_message, pause, results, Scene, Transition};
+use ggez::event::{Axis, Button, GamepadId, KeyCode};
+use ggez::*;
+
+pub struct InGame {
+    game: game::GameState,
+    results_shown: bool,
+}
+
+impl InGame {
+    pub fn new(game: game::GameState) -> Self {
+        InGame {
+            game: game,
+            results_shown: false,
+        }
+    }
+}
+
+impl Scene for InGame {
+    fn update(&mut self, ctx: &mut Context, ticks: u32) -> GameResult<Transition> {
+        if self.game.is_finished() {
+            // results screen is popped only when a restart is requested
+            if self.results_shown {
+                if let Err(error) = self.game.restart() {
+                    return Ok(Transition::Replace(Box::new(
+                        error_message::ErrorMessage::new(error),
+                    )));
+                }
+                self.results_shown = false;
+            } else {
+                if let Err(error) = self.game.save_recording() {
+                    return Ok(Transition::Push(Box::new(
+                        error_message::ErrorMessage::new(error),
+                    )));
+                }
+                self.results_shown = true;
+                return Ok(Transition::Push(Box::new(results::Results::new(
+                    &self.game,
+                ))));
+            }
+        }
+        if self.game.check_gamepads(ctx) {
+            return Ok(Transition::Push(Box::new(pause::Pause::new(
+                self.game.bindings.pause.0,
+            ))));
+        }
+        self.game.update(ctx, ticks);
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        self.game.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if self.game.is_pause_key(keycode) {
+            return Transition::Push(Box::new(pause::Pause::new(self.game.bindings.pause.0)));
+        }
+        self.game.update_input(keycode, true);
+        Transition::None
+    }
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode) {
+        self.game.update_input(keycode, false);
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.game.update_mouse(x, y);
+    }
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        id: GamepadId,
+    ) -> Transition {
+        if button == Button::Start {
+            return Transition::Push(Box::new(pause::Pause::new(self.game.bindings.pause.0)));
+        }
+        self.game.update_gamepad_button(button, id, true);
+        Transition::None
+    }
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, button: Button, id: GamepadId) {
+        self.game.update_gamepad_button(button, id, false);
+    }
+    fn gamepad_axis_event(&mut self, _ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        self.game.update_gamepad_axis(axis, value, id);
+    }
+    fn exit(&mut self) -> Result<(), ConfigError> {
+        self.game.save_recording()
+    }
+    fn resume(&mut self) {
+        // bindings may have changed, and keys released meanwhile never reached the game
+        self.game.reload_bindings();
+    }
+}
@@ -0,0 +1,166 @@
+use crate::bindings;
+use crate::scene::{error_message, menu, Scene, Transition};
+use crate::settings;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::*;
+
+pub struct KeyBindings {
+    menu: menu::Menu,
+    bindings: bindings::Bindings,
+    // binding waiting for a key press
+    waiting: Option<bindings::Binding>,
+    message: Option<String>,
+}
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        let (bindings, message) = match bindings::Bindings::load_current() {
+            Ok(bindings) => (bindings, None),
+            Err(error) => (bindings::Bindings::default(), Some(error.to_string())),
+        };
+        let mut key_bindings = KeyBindings {
+            menu: menu::Menu::new(Vec::new(), 250.0, 100.0),
+            bindings: bindings,
+            waiting: None,
+            message: message,
+        };
+        key_bindings.update_items();
+        key_bindings
+    }
+    fn update_items(&mut self) {
+        let mut items: Vec<String> = self
+            .bindings
+            .list()
+            .iter()
+            .map(|binding| {
+                if self.waiting == Some(*binding) {
+                    format!("{}: press a key", binding.label())
+                } else {
+                    format!(
+                        "{}: {}",
+                        binding.label(),
+                        String::from(self.bindings.get(binding))
+                    )
+                }
+            })
+            .collect();
+        items.push(match self.bindings.mouse_player {
+            Some(player) => format!("Mouse: P{}", player + 1),
+            None => String::from("Mouse: off"),
+        });
+        items.push(String::from("Reset to defaults"));
+        items.push(String::from("Save"));
+        items.push(String::from("Back"));
+        self.menu.items = items;
+    }
+    fn check_conflicts(&mut self) {
+        self.message = self.bindings.find_conflict().map(|(first, second)| {
+            format!(
+                "{} and {} share the same key",
+                first.label(),
+                second.label()
+            )
+        });
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        let list = self.bindings.list();
+        if let Some(binding) = list.get(index) {
+            self.waiting = Some(*binding);
+            self.update_items();
+            return Transition::None;
+        }
+        match index - list.len() {
+            0 => {
+                self.bindings.next_mouse_player();
+                self.update_items();
+                Transition::None
+            }
+            1 => {
+                // the shipped file holds the defaults, saved changes go to the user file
+                self.bindings =
+                    bindings::Bindings::load(settings::BINDINGS_FILE).unwrap_or_default();
+                self.check_conflicts();
+                self.update_items();
+                Transition::None
+            }
+            2 => {
+                if self.bindings.find_conflict().is_some() {
+                    return Transition::None;
+                }
+                match self.bindings.save(settings::USER_BINDINGS_FILE) {
+                    Ok(()) => Transition::Pop,
+                    Err(error) => {
+                        Transition::Push(Box::new(error_message::ErrorMessage::new(error)))
+                    }
+                }
+            }
+            _ => Transition::Pop,
+        }
+    }
+}
+
+impl Scene for KeyBindings {
+    fn update(&mut self, _ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, graphics::BLACK);
+        menu::draw_title(ctx, "Controls", 250.0, 40.0)?;
+        if let Some(message) = &self.message {
+            graphics::draw(
+                ctx,
+                &graphics::Text::new(message.as_str()),
+                graphics::DrawParam::from((
+                    mint::Point2 { x: 100.0, y: 520.0 },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    graphics::Color::from_rgb(255, 120, 120),
+                )),
+            )?;
+        }
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if let Some(binding) = self.waiting.take() {
+            // escape cancels, so it can only be bound by editing the file or resetting
+            if keycode != KeyCode::Escape {
+                if bindings::key_name(keycode).is_some() {
+                    self.bindings.set(&binding, bindings::Key(keycode));
+                    self.check_conflicts();
+                } else {
+                    self.message = Some(String::from("This key can not be bound"));
+                }
+            }
+            self.update_items();
+            return Transition::None;
+        }
+        if keycode == KeyCode::Escape {
+            return Transition::Pop;
+        }
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        if self.waiting.is_some() {
+            return Transition::None;
+        }
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        if self.waiting.is_none() {
+            self.menu.mouse_motion_event(x, y);
+        }
+    }
+}
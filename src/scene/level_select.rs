@@ -0,0 +1,84 @@
+use crate::config::ConfigError;
+use crate::game;
+use crate::gamemode;
+use crate::scene::{error_message, lobby, menu, Scene, Transition};
+use ggez::event::{KeyCode, MouseButton};
+use ggez::*;
+
+pub struct LevelSelect {
+    menu: menu::Menu,
+    level_count: usize,
+    gamemode_path: String,
+    difficulty: gamemode::Difficulty,
+    theme_path: String,
+}
+
+impl LevelSelect {
+    pub fn new(
+        gamemode_path: &str,
+        difficulty: gamemode::Difficulty,
+        theme_path: &str,
+    ) -> Result<Self, ConfigError> {
+        let mode = gamemode::GameMode::new(gamemode_path, difficulty)?;
+        let mut items: Vec<String> = mode.levels.iter().map(|level| level.name.clone()).collect();
+        items.push(String::from("Back"));
+        Ok(LevelSelect {
+            menu: menu::Menu::new(items, 250.0, 200.0),
+            level_count: mode.levels.len(),
+            gamemode_path: String::from(gamemode_path),
+            difficulty: difficulty,
+            theme_path: String::from(theme_path),
+        })
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        if index < self.level_count {
+            match game::GameState::new(
+                &self.gamemode_path,
+                self.difficulty,
+                index,
+                &self.theme_path,
+                rand::random(),
+            ) {
+                Ok(game) => Transition::Push(Box::new(lobby::Lobby::new(game))),
+                Err(error) => Transition::Push(Box::new(error_message::ErrorMessage::new(error))),
+            }
+        } else {
+            Transition::Pop
+        }
+    }
+}
+
+impl Scene for LevelSelect {
+    fn update(&mut self, _ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, graphics::BLACK);
+        menu::draw_title(ctx, "Select level", 250.0, 120.0)?;
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if keycode == KeyCode::Escape {
+            return Transition::Pop;
+        }
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.menu.mouse_motion_event(x, y);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::game;
+use crate::scene::{in_game, menu, Scene, Transition};
+use ggez::event::{Button, GamepadId, KeyCode, MouseButton};
+use ggez::*;
+
+pub struct Lobby {
+    menu: menu::Menu,
+    game: Option<game::GameState>,
+    players: Vec<Seat>,
+}
+
+struct Seat {
+    number: usize,
+    ai: bool,
+    // claimed gamepad with its name
+    device: Option<(GamepadId, String)>,
+}
+
+impl Lobby {
+    pub fn new(game: game::GameState) -> Self {
+        let players = game
+            .simulation
+            .players
+            .iter()
+            .map(|player| Seat {
+                number: player.player.index() + 1,
+                ai: player.ai.is_some(),
+                device: None,
+            })
+            .collect();
+        Lobby {
+            menu: menu::Menu::new(
+                vec![String::from("Start"), String::from("Back")],
+                250.0,
+                400.0,
+            ),
+            game: Some(game),
+            players: players,
+        }
+    }
+    fn claim(&mut self, ctx: &Context, id: GamepadId) {
+        if self.is_claimed(id) {
+            return;
+        }
+        if let Some(seat) = self
+            .players
+            .iter_mut()
+            .find(|seat| !seat.ai && seat.device.is_none())
+        {
+            let name = String::from(input::gamepad::gamepad(ctx, id).name());
+            seat.device = Some((id, name));
+        }
+    }
+    fn release(&mut self, id: GamepadId) {
+        for seat in self.players.iter_mut() {
+            if let Some((claimed, _)) = seat.device {
+                if claimed == id {
+                    seat.device = None;
+                }
+            }
+        }
+    }
+    fn is_claimed(&self, id: GamepadId) -> bool {
+        self.players.iter().any(|seat| match seat.device {
+            Some((claimed, _)) => claimed == id,
+            None => false,
+        })
+    }
+    fn start(&mut self) -> Transition {
+        match self.game.take() {
+            Some(mut game) => {
+                game.gamepads = self
+                    .players
+                    .iter()
+                    .map(|seat| seat.device.as_ref().map(|(id, _)| *id))
+                    .collect();
+                Transition::Replace(Box::new(in_game::InGame::new(game)))
+            }
+            None => Transition::None,
+        }
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        match index {
+            0 => self.start(),
+            _ => Transition::Pop,
+        }
+    }
+}
+
+impl Scene for Lobby {
+    fn update(&mut self, ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        // unplugged gamepads lose their claim
+        for seat in self.players.iter_mut() {
+            if let Some((id, _)) = seat.device {
+                if !input::gamepad::gamepad(ctx, id).is_connected() {
+                    seat.device = None;
+                }
+            }
+        }
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, graphics::BLACK);
+        menu::draw_title(ctx, "Lobby", 250.0, 120.0)?;
+        let mut lines: Vec<String> = self
+            .players
+            .iter()
+            .map(|seat| match &seat.device {
+                _ if seat.ai => format!("P{}: AI", seat.number),
+                Some((_, name)) => format!("P{}: Gamepad ({})", seat.number, name),
+                None => format!("P{}: Keyboard", seat.number),
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(String::from("Press A on a gamepad to join, B to leave"));
+        for (index, line) in lines.iter().enumerate() {
+            graphics::draw(
+                ctx,
+                &graphics::Text::new(line.as_str()),
+                graphics::DrawParam::from((
+                    mint::Point2 {
+                        x: 250.0,
+                        y: 200.0 + 24.0 * index as f32,
+                    },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    graphics::WHITE,
+                )),
+            )?;
+        }
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if keycode == KeyCode::Escape {
+            return Transition::Pop;
+        }
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.menu.mouse_motion_event(x, y);
+    }
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: Button,
+        id: GamepadId,
+    ) -> Transition {
+        // gamepads navigate the menu only once they joined
+        match button {
+            Button::South if !self.is_claimed(id) => {
+                self.claim(ctx, id);
+                Transition::None
+            }
+            Button::East => {
+                self.release(id);
+                Transition::None
+            }
+            Button::Start => self.start(),
+            _ => match self.menu.gamepad_button_down_event(button) {
+                Some(index) => self.activate(index),
+                None => Transition::None,
+            },
+        }
+    }
+}
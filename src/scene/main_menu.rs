@@ -0,0 +1,92 @@
+use crate::scene::{
+    file_stem, key_bindings, list_config_files, menu, mode_select, Scene, Transition,
+};
+use crate::settings;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::*;
+
+pub struct MainMenu {
+    menu: menu::Menu,
+    themes: Vec<String>,
+    theme_index: usize,
+}
+
+impl MainMenu {
+    pub fn new() -> Self {
+        let themes = list_config_files(settings::THEME_DIR);
+        let theme_index = themes
+            .iter()
+            .position(|theme| theme == settings::DEFAULT_THEME)
+            .unwrap_or(0);
+        let mut main_menu = MainMenu {
+            menu: menu::Menu::new(Vec::new(), 300.0, 250.0),
+            themes: themes,
+            theme_index: theme_index,
+        };
+        main_menu.menu.items = vec![
+            String::from("Play"),
+            main_menu.theme_label(),
+            String::from("Controls"),
+            String::from("Quit"),
+        ];
+        main_menu
+    }
+    fn theme_path(&self) -> String {
+        match self.themes.get(self.theme_index) {
+            Some(theme) => theme.clone(),
+            None => String::from(settings::DEFAULT_THEME),
+        }
+    }
+    fn theme_label(&self) -> String {
+        format!("Theme: {}", file_stem(&self.theme_path()))
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        match index {
+            0 => Transition::Push(Box::new(mode_select::ModeSelect::new(&self.theme_path()))),
+            1 => {
+                if !self.themes.is_empty() {
+                    self.theme_index = (self.theme_index + 1) % self.themes.len();
+                }
+                self.menu.items[1] = self.theme_label();
+                Transition::None
+            }
+            2 => Transition::Push(Box::new(key_bindings::KeyBindings::new())),
+            _ => Transition::Quit,
+        }
+    }
+}
+
+impl Scene for MainMenu {
+    fn update(&mut self, _ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, graphics::BLACK);
+        menu::draw_title(ctx, "HexPong", 300.0, 150.0)?;
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if keycode == KeyCode::Escape {
+            return Transition::Quit;
+        }
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.menu.mouse_motion_event(x, y);
+    }
+}
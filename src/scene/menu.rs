@@ -0,0 +1,129 @@
+use crate::settings;
+use ggez::event::{Button, KeyCode, MouseButton};
+use ggez::*;
+
+const ITEM_HEIGHT: f32 = 24.0;
+const CHAR_WIDTH: f32 = 8.0;
+
+pub struct Menu {
+    pub items: Vec<String>,
+    pub selected: usize,
+    position: mint::Point2<f32>,
+}
+
+impl Menu {
+    pub fn new(items: Vec<String>, x: f32, y: f32) -> Self {
+        Menu {
+            items: items,
+            selected: 0,
+            position: mint::Point2 { x: x, y: y },
+        }
+    }
+    pub fn key_down_event(&mut self, keycode: KeyCode) -> Option<usize> {
+        match keycode {
+            KeyCode::Up => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+                None
+            }
+            KeyCode::Down => {
+                self.selected = (self.selected + 1) % self.items.len();
+                None
+            }
+            KeyCode::Return | KeyCode::Space => Some(self.selected),
+            _ => None,
+        }
+    }
+    pub fn gamepad_button_down_event(&mut self, button: Button) -> Option<usize> {
+        match button {
+            Button::DPadUp => self.key_down_event(KeyCode::Up),
+            Button::DPadDown => self.key_down_event(KeyCode::Down),
+            Button::South => Some(self.selected),
+            _ => None,
+        }
+    }
+    pub fn mouse_motion_event(&mut self, x: f32, y: f32) {
+        if let Some(index) = self.item_at(x, y) {
+            self.selected = index;
+        }
+    }
+    pub fn mouse_button_down_event(
+        &mut self,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Option<usize> {
+        if button == MouseButton::Left {
+            self.item_at(x, y)
+        } else {
+            None
+        }
+    }
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        for (index, item) in self.items.iter().enumerate() {
+            let text = if index == self.selected {
+                graphics::Text::new(format!("> {}", item))
+            } else {
+                graphics::Text::new(format!("  {}", item))
+            };
+            graphics::draw(
+                ctx,
+                &text,
+                graphics::DrawParam::from((
+                    mint::Point2 {
+                        x: self.position.x,
+                        y: self.position.y + ITEM_HEIGHT * index as f32,
+                    },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    if index == self.selected {
+                        graphics::WHITE
+                    } else {
+                        [0.6, 0.6, 0.6, 1.0].into()
+                    },
+                )),
+            )?;
+        }
+        Ok(())
+    }
+    fn item_at(&self, x: f32, y: f32) -> Option<usize> {
+        if y < self.position.y {
+            return None;
+        }
+        let index = ((y - self.position.y) / ITEM_HEIGHT) as usize;
+        match self.items.get(index) {
+            Some(item)
+                if x >= self.position.x
+                    && x <= self.position.x + CHAR_WIDTH * (item.len() + 2) as f32 =>
+            {
+                Some(index)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn draw_title(ctx: &mut Context, title: &str, x: f32, y: f32) -> GameResult {
+    graphics::draw(
+        ctx,
+        &graphics::Text::new(title),
+        graphics::DrawParam::from((
+            mint::Point2 { x: x, y: y },
+            0.0,
+            mint::Point2 { x: 0.0, y: 0.0 },
+            mint::Vector2 { x: 2.0, y: 2.0 },
+            graphics::WHITE,
+        )),
+    )
+}
+
+pub fn draw_shade(ctx: &mut Context) -> GameResult {
+    let (width, height) = settings::SCREEN_SIZE;
+    let shade = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(0.0, 0.0, width, height),
+        [0.0, 0.0, 0.0, 0.7].into(),
+    )?;
+    graphics::draw(ctx, &shade, graphics::DrawParam::default())
+}
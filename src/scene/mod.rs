@@ -0,0 +1,227 @@
+use crate::config;
+use crate::settings;
+use ggez::event::{Axis, Button, GamepadId, KeyCode, KeyMods, MouseButton};
+use ggez::*;
+
+pub mod error_message;
+pub mod in_game;
+pub mod key_bindings;
+pub mod level_select;
+pub mod lobby;
+pub mod main_menu;
+pub mod menu;
+pub mod mode_select;
+pub mod pause;
+pub mod results;
+
+pub enum Transition {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+    Reset(Box<dyn Scene>),
+    Quit,
+}
+
+pub trait Scene {
+    fn update(&mut self, ctx: &mut Context, ticks: u32) -> GameResult<Transition>;
+    fn draw(&mut self, ctx: &mut Context) -> GameResult;
+    fn key_down_event(&mut self, _ctx: &mut Context, _keycode: KeyCode) -> Transition {
+        Transition::None
+    }
+    fn key_up_event(&mut self, _ctx: &mut Context, _keycode: KeyCode) {}
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> Transition {
+        Transition::None
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {}
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: Button,
+        _id: GamepadId,
+    ) -> Transition {
+        Transition::None
+    }
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, _button: Button, _id: GamepadId) {}
+    fn gamepad_axis_event(&mut self, _ctx: &mut Context, _axis: Axis, _value: f32, _id: GamepadId) {
+    }
+    // an error is shown once the transition that removed the scene is done
+    fn exit(&mut self) -> Result<(), config::ConfigError> {
+        Ok(())
+    }
+    // called when the scene above this one is popped
+    fn resume(&mut self) {}
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(scene: Box<dyn Scene>) -> Self {
+        SceneStack {
+            scenes: vec![scene],
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) {
+        let mut errors = Vec::new();
+        match transition {
+            Transition::None => (),
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                if let Some(mut scene) = self.scenes.pop() {
+                    errors.extend(scene.exit().err());
+                }
+                match self.scenes.last_mut() {
+                    Some(scene) => scene.resume(),
+                    None => event::quit(ctx),
+                }
+            }
+            Transition::Replace(scene) => {
+                if let Some(mut scene) = self.scenes.pop() {
+                    errors.extend(scene.exit().err());
+                }
+                self.scenes.push(scene);
+            }
+            Transition::Reset(scene) => {
+                errors = self.exit_all();
+                self.scenes.push(scene);
+            }
+            Transition::Quit => {
+                errors = self.exit_all();
+                event::quit(ctx);
+            }
+        }
+        for error in errors {
+            // there is nothing left to show the error on when quitting
+            if self.scenes.is_empty() {
+                eprintln!("{}", error);
+            } else {
+                self.scenes
+                    .push(Box::new(error_message::ErrorMessage::new(error)));
+            }
+        }
+    }
+
+    fn exit_all(&mut self) -> Vec<config::ConfigError> {
+        let mut errors = Vec::new();
+        while let Some(mut scene) = self.scenes.pop() {
+            errors.extend(scene.exit().err());
+        }
+        errors
+    }
+}
+
+impl event::EventHandler for SceneStack {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        // the timestep is consumed here, so that a paused game does not catch up on resume
+        let mut ticks = 0;
+        while timer::check_update_time(ctx, settings::TICK_RATE) {
+            ticks += 1;
+        }
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.update(ctx, ticks)?,
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+        Ok(())
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut first = self.scenes.len().saturating_sub(1);
+        while first > 0 && self.scenes[first].is_overlay() {
+            first -= 1;
+        }
+        for scene in self.scenes[first..].iter_mut() {
+            scene.draw(ctx)?;
+        }
+        graphics::present(ctx)?;
+        Ok(())
+    }
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymod: KeyMods,
+        repeat: bool,
+    ) {
+        if repeat {
+            return;
+        }
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.key_down_event(ctx, keycode),
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+    }
+    fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.key_up_event(ctx, keycode);
+        }
+    }
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.mouse_button_down_event(ctx, button, x, y),
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+    }
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.mouse_motion_event(ctx, x, y);
+        }
+    }
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, button: Button, id: GamepadId) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.gamepad_button_down_event(ctx, button, id),
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+    }
+    fn gamepad_button_up_event(&mut self, ctx: &mut Context, button: Button, id: GamepadId) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.gamepad_button_up_event(ctx, button, id);
+        }
+    }
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.gamepad_axis_event(ctx, axis, value, id);
+        }
+    }
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.exit_all();
+        false
+    }
+}
+
+pub fn list_config_files(dir: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let file_path = path.to_string_lossy().into_owned();
+            if config::is_config_file(&file_path) {
+                files.push(file_path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+pub fn file_stem(file_path: &str) -> String {
+    std::path::Path::new(file_path)
+        .file_stem()
+        .map_or(String::from(file_path), |stem| {
+            stem.to_string_lossy().into_owned()
+        })
+}
@@ -0,0 +1,94 @@
+use crate::gamemode;
+use crate::scene::{
+    error_message, file_stem, level_select, list_config_files, menu, Scene, Transition,
+};
+use crate::settings;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::*;
+
+const DIFFICULTIES: [gamemode::Difficulty; 3] = [
+    gamemode::Difficulty::Easy,
+    gamemode::Difficulty::Normal,
+    gamemode::Difficulty::Hard,
+];
+
+pub struct ModeSelect {
+    menu: menu::Menu,
+    gamemodes: Vec<String>,
+    difficulty_index: usize,
+    theme_path: String,
+}
+
+impl ModeSelect {
+    pub fn new(theme_path: &str) -> Self {
+        let gamemodes = list_config_files(settings::GAMEMODE_DIR);
+        let mut items = vec![difficulty_label(0)];
+        for gamemode in gamemodes.iter() {
+            items.push(file_stem(gamemode));
+        }
+        items.push(String::from("Back"));
+        ModeSelect {
+            menu: menu::Menu::new(items, 250.0, 200.0),
+            gamemodes: gamemodes,
+            difficulty_index: 0,
+            theme_path: String::from(theme_path),
+        }
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        if index == 0 {
+            self.difficulty_index = (self.difficulty_index + 1) % DIFFICULTIES.len();
+            self.menu.items[0] = difficulty_label(self.difficulty_index);
+            return Transition::None;
+        }
+        match self.gamemodes.get(index - 1) {
+            Some(gamemode) => match level_select::LevelSelect::new(
+                gamemode,
+                DIFFICULTIES[self.difficulty_index],
+                &self.theme_path,
+            ) {
+                Ok(level_select) => Transition::Push(Box::new(level_select)),
+                Err(error) => Transition::Push(Box::new(error_message::ErrorMessage::new(error))),
+            },
+            None => Transition::Pop,
+        }
+    }
+}
+
+fn difficulty_label(difficulty_index: usize) -> String {
+    format!("Difficulty: {}", DIFFICULTIES[difficulty_index].to_str())
+}
+
+impl Scene for ModeSelect {
+    fn update(&mut self, _ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, graphics::BLACK);
+        menu::draw_title(ctx, "Select gamemode", 250.0, 120.0)?;
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if keycode == KeyCode::Escape {
+            return Transition::Pop;
+        }
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.menu.mouse_motion_event(x, y);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::bindings;
+use crate::scene::{key_bindings, main_menu, menu, Scene, Transition};
+use ggez::event::{Button, GamepadId, KeyCode, MouseButton};
+use ggez::*;
+
+pub struct Pause {
+    menu: menu::Menu,
+    pause_key: KeyCode,
+}
+
+impl Pause {
+    pub fn new(pause_key: KeyCode) -> Self {
+        Pause {
+            menu: menu::Menu::new(
+                vec![
+                    String::from("Resume"),
+                    String::from("Controls"),
+                    String::from("Main menu"),
+                    String::from("Quit"),
+                ],
+                330.0,
+                250.0,
+            ),
+            pause_key: pause_key,
+        }
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        match index {
+            0 => Transition::Pop,
+            1 => Transition::Push(Box::new(key_bindings::KeyBindings::new())),
+            2 => Transition::Reset(Box::new(main_menu::MainMenu::new())),
+            _ => Transition::Quit,
+        }
+    }
+}
+
+impl Scene for Pause {
+    fn update(&mut self, _ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        menu::draw_shade(ctx)?;
+        menu::draw_title(ctx, "Paused", 330.0, 170.0)?;
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        if keycode == self.pause_key {
+            return Transition::Pop;
+        }
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.menu.mouse_motion_event(x, y);
+    }
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        _id: GamepadId,
+    ) -> Transition {
+        if button == Button::Start {
+            return Transition::Pop;
+        }
+        match self.menu.gamepad_button_down_event(button) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn is_overlay(&self) -> bool {
+        true
+    }
+    fn resume(&mut self) {
+        // the pause key may have been rebound in the controls menu
+        if let Ok(bindings) = bindings::Bindings::load_current() {
+            self.pause_key = bindings.pause.0;
+        }
+    }
+}
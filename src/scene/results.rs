@@ -0,0 +1,131 @@
+use crate::component::controller;
+use crate::game;
+use crate::scene::{main_menu, menu, Scene, Transition};
+use crate::simulation;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::*;
+
+pub struct Results {
+    menu: menu::Menu,
+    title: String,
+    outcome: Option<String>,
+    scores: Vec<(String, graphics::Color)>,
+}
+
+impl Results {
+    pub fn new(game: &game::GameState) -> Self {
+        let title = match game.simulation.phase {
+            simulation::Phase::Victory => "Victory!",
+            _ => "Game over",
+        };
+        let name = |player: &controller::Player| {
+            if game.simulation.is_ai(*player) {
+                format!("Player {} (AI)", player.index() + 1)
+            } else {
+                format!("Player {}", player.index() + 1)
+            }
+        };
+        let outcome = match (&game.simulation.winner, &game.simulation.loser) {
+            (Some(winner), _) => Some(format!("{} wins", name(winner))),
+            (None, Some(loser)) => Some(format!("{} is out of lives", name(loser))),
+            (None, None) => None,
+        };
+        let scores = game
+            .simulation
+            .players
+            .iter()
+            .map(|player| {
+                (
+                    format!("Player {}: {}", player.player.index() + 1, player.score),
+                    game.theme.get_player_color(&player.player),
+                )
+            })
+            .collect();
+        Results {
+            menu: menu::Menu::new(
+                vec![
+                    String::from("Restart"),
+                    String::from("Main menu"),
+                    String::from("Quit"),
+                ],
+                330.0,
+                350.0,
+            ),
+            title: String::from(title),
+            outcome: outcome,
+            scores: scores,
+        }
+    }
+    fn activate(&mut self, index: usize) -> Transition {
+        match index {
+            // popping back into a finished game restarts it
+            0 => Transition::Pop,
+            1 => Transition::Reset(Box::new(main_menu::MainMenu::new())),
+            _ => Transition::Quit,
+        }
+    }
+}
+
+impl Scene for Results {
+    fn update(&mut self, _ctx: &mut Context, _ticks: u32) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        menu::draw_shade(ctx)?;
+        menu::draw_title(ctx, &self.title, 330.0, 170.0)?;
+        if let Some(outcome) = &self.outcome {
+            graphics::draw(
+                ctx,
+                &graphics::Text::new(outcome.as_str()),
+                graphics::DrawParam::from((
+                    mint::Point2 { x: 330.0, y: 210.0 },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    graphics::WHITE,
+                )),
+            )?;
+        }
+        for (index, (score, color)) in self.scores.iter().enumerate() {
+            graphics::draw(
+                ctx,
+                &graphics::Text::new(score.as_str()),
+                graphics::DrawParam::from((
+                    mint::Point2 {
+                        x: 330.0,
+                        y: 240.0 + 20.0 * index as f32,
+                    },
+                    0.0,
+                    mint::Point2 { x: 0.0, y: 0.0 },
+                    mint::Vector2 { x: 1.0, y: 1.0 },
+                    *color,
+                )),
+            )?;
+        }
+        self.menu.draw(ctx)
+    }
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode) -> Transition {
+        match self.menu.key_down_event(keycode) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_button_down_event(button, x, y) {
+            Some(index) => self.activate(index),
+            None => Transition::None,
+        }
+    }
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.menu.mouse_motion_event(x, y);
+    }
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
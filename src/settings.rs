@@ -3,7 +3,20 @@ pub const SCREEN_SIZE: (f32, f32) = (800.0, 600.0);
 pub const UNIT_SIZE: f32 = 1000.0;
 pub const ORIGIN: (f32, f32) = (SCREEN_SIZE.0 / 2.0, SCREEN_SIZE.1 / 2.0);
 pub const BALL_SPAWN: (f32, f32) = (0.0, 0.5 * UNIT_SIZE);
-pub const NUMBER_PLAYERS: usize = 1;
+pub const TICK_RATE: u32 = 60;
+pub const TICK_DURATION: f32 = 1.0 / TICK_RATE as f32;
+pub const LEVEL_TRANSITION_TIME: f32 = 2.0;
+pub const LAUNCH_TIMEOUT: f32 = 3.0;
+pub const GAMEPAD_DEADZONE: f32 = 0.2;
+pub const MAX_BOUNCES: u32 = 4;
+pub const MAX_BALLS: usize = 12;
+pub const REPLAY_DIR: &str = "replays";
+pub const BINDINGS_FILE: &str = "config/bindings.yaml";
+pub const USER_BINDINGS_FILE: &str = "bindings.yaml";
+pub const GAMEMODE_DIR: &str = "config/gamemodes";
+pub const LEVEL_DIR: &str = "config/levels";
+pub const THEME_DIR: &str = "config/themes";
+pub const DEFAULT_THEME: &str = "config/themes/base.yaml";
 
 pub fn get_origin() -> mint::Point2<f32> {
     mint::Point2 {
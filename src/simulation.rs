@@ -0,0 +1,1074 @@
+use crate::brain;
+use crate::component::{ball, block, controller, wall};
+use crate::gamemode;
+use crate::levels;
+use crate::settings;
+use crate::sweep;
+use crate::InputState;
+use crate::PhysicalComponent;
+use geometry::base::{Point, Vector};
+use geometry::shape::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub enum Event {
+    BarHit,
+    WallHit,
+    BlockHit { destroyed: bool },
+    // blast covering a circle in unit coordinates
+    Explosion { x: f32, y: f32, radius: f32 },
+    BallLost { side: gamemode::Side },
+    BallHit,
+    BallNudged,
+    BallLaunched,
+    MultiballSpawned,
+    LevelCompleted,
+    LevelStarted,
+    GameOver,
+    Victory,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Phase {
+    Playing,
+    Transition { ticks_left: u32 },
+    GameOver,
+    Victory,
+}
+
+const PUSH_OUT_MARGIN: f32 = 0.01;
+// degrees between the balls spawned by a multiball block
+const MULTIBALL_SPREAD: f32 = 30.0;
+
+struct BlockDamage {
+    ball_index: usize,
+    block_index: usize,
+    owner: Option<controller::Player>,
+}
+
+struct BallLoss {
+    ball_index: usize,
+    side: gamemode::Side,
+}
+
+#[derive(Clone, Copy)]
+enum Obstacle {
+    Wall,
+    Bar {
+        player_index: usize,
+        bar_index: usize,
+    },
+    Block(usize),
+}
+
+pub struct Simulation {
+    pub players: Vec<controller::Controller>,
+    brains: Vec<Box<dyn brain::ControllerBrain>>,
+    pub walls: Vec<wall::Wall>,
+    pub level: levels::Level,
+    pub level_index: usize,
+    pub phase: Phase,
+    levels: Vec<levels::Level>,
+    pub balls: Vec<ball::Ball>,
+    // ticks until a freshly spawned ball launches by itself, zero once it is in play
+    pub launch_ticks: u32,
+    pub ball_speed: f32,
+    pub ball_collisions: bool,
+    pub english: gamemode::English,
+    pub deadlock: gamemode::Deadlock,
+    pub points: gamemode::Points,
+    pub lives: gamemode::Lives,
+    pub shared_lives: u32,
+    pub game_over: gamemode::GameOver,
+    pub win_conditions: Vec<gamemode::WinCondition>,
+    // player that reached a points condition, and player that ran out of lives
+    pub winner: Option<controller::Player>,
+    pub loser: Option<controller::Player>,
+    pub seed: u64,
+    pub tick: u64,
+    rng: StdRng,
+}
+
+fn launch_timeout() -> u32 {
+    (settings::LAUNCH_TIMEOUT * settings::TICK_RATE as f32) as u32
+}
+
+impl Simulation {
+    pub fn new(mode: gamemode::GameMode, start_level: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut players = mode.players;
+        for player in players.iter_mut() {
+            player.lives = mode.lives.count;
+        }
+        // AIs get their own random streams, so they do not change the ball spawns
+        let brains = players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| brain::create(player.ai, seed.wrapping_add(index as u64 + 1)))
+            .collect();
+        Simulation {
+            players: players,
+            brains: brains,
+            walls: mode.walls,
+            level: mode.levels[start_level].clone(),
+            level_index: start_level,
+            phase: Phase::Playing,
+            levels: mode.levels,
+            balls: vec![ball::Ball::new(
+                Point::new(settings::BALL_SPAWN.0, settings::BALL_SPAWN.1),
+                mode.ball_speed,
+                &mut rng,
+            )],
+            launch_ticks: launch_timeout(),
+            ball_speed: mode.ball_speed,
+            ball_collisions: mode.ball_collisions,
+            english: mode.english,
+            deadlock: mode.deadlock,
+            points: mode.points,
+            lives: mode.lives,
+            shared_lives: mode.lives.count,
+            game_over: mode.game_over,
+            win_conditions: mode.win_conditions,
+            winner: None,
+            loser: None,
+            seed: seed,
+            tick: 0,
+            rng: rng,
+        }
+    }
+
+    pub fn step(&mut self, inputs: &[InputState]) -> Vec<Event> {
+        let mut events = Vec::new();
+        for (index, (player, brain)) in self
+            .players
+            .iter_mut()
+            .zip(self.brains.iter_mut())
+            .enumerate()
+        {
+            let input = inputs.get(index).copied().unwrap_or_default();
+            player.input = brain.decide(player, &self.balls, &self.walls, input);
+        }
+        for player in self.players.iter_mut() {
+            player.update();
+        }
+        match self.phase {
+            Phase::Playing => {
+                for block in self.level.blocks.iter_mut() {
+                    block.update();
+                }
+                for wall in self.walls.iter_mut() {
+                    wall.update();
+                }
+                if self.launch_ticks > 0 {
+                    if self.players.iter().any(|player| player.input.launch)
+                        || self.launch_ticks == 1
+                    {
+                        self.launch_ticks = 0;
+                        events.push(Event::BallLaunched);
+                    } else {
+                        self.launch_ticks -= 1;
+                    }
+                }
+                // balls are moved by the collision pass, one bounce at a time
+                if self.launch_ticks == 0 {
+                    self.collision(&mut events);
+                    if self.ball_collisions {
+                        self.ball_collision(&mut events);
+                    }
+                }
+                if self.is_game_over() {
+                    self.loser = self.out_of_lives();
+                    // an AI running out of lives is a win for the humans still playing
+                    let victory = self.loser.map_or(false, |loser| self.is_ai(loser))
+                        && self
+                            .players
+                            .iter()
+                            .any(|player| player.ai.is_none() && player.lives > 0);
+                    self.finish(victory, &mut events);
+                } else if self.is_won() {
+                    self.winner = self.points_winner();
+                    // AI players can win too, which is no victory for the humans
+                    let victory = !self.winner.map_or(false, |winner| self.is_ai(winner));
+                    self.finish(victory, &mut events);
+                } else if self.level.is_completed() {
+                    self.balls.clear();
+                    self.phase = Phase::Transition {
+                        ticks_left: (settings::LEVEL_TRANSITION_TIME * settings::TICK_RATE as f32)
+                            as u32,
+                    };
+                    events.push(Event::LevelCompleted);
+                }
+            }
+            Phase::Transition { ticks_left } => {
+                if ticks_left > 1 {
+                    self.phase = Phase::Transition {
+                        ticks_left: ticks_left - 1,
+                    };
+                } else {
+                    self.next_level(&mut events);
+                }
+            }
+            Phase::GameOver | Phase::Victory => (),
+        }
+        self.tick += 1;
+        events
+    }
+
+    pub fn level_number(&self) -> usize {
+        self.level_index + 1
+    }
+
+    pub fn elapsed_time(&self) -> f32 {
+        self.tick as f32 * settings::TICK_DURATION
+    }
+
+    pub fn is_ai(&self, player: controller::Player) -> bool {
+        self.players
+            .iter()
+            .any(|controller| controller.player == player && controller.ai.is_some())
+    }
+
+    fn finish(&mut self, victory: bool, events: &mut Vec<Event>) {
+        self.balls.clear();
+        if victory {
+            self.phase = Phase::Victory;
+            events.push(Event::Victory);
+        } else {
+            self.phase = Phase::GameOver;
+            events.push(Event::GameOver);
+        }
+    }
+
+    fn is_game_over(&self) -> bool {
+        if self.lives.shared {
+            return self.shared_lives == 0;
+        }
+        match self.game_over {
+            gamemode::GameOver::AnyPlayer => self.players.iter().any(|player| player.lives == 0),
+            gamemode::GameOver::AllPlayers => self.players.iter().all(|player| player.lives == 0),
+        }
+    }
+
+    fn is_won(&self) -> bool {
+        self.win_conditions.iter().any(|condition| match condition {
+            // cleared blocks are checked on level completion
+            gamemode::WinCondition::ClearBlocks => false,
+            gamemode::WinCondition::Points(points) => {
+                self.players.iter().any(|player| player.score >= *points)
+            }
+            gamemode::WinCondition::Survive(time) => self.elapsed_time() >= *time,
+        })
+    }
+
+    fn out_of_lives(&self) -> Option<controller::Player> {
+        if self.lives.shared {
+            return None;
+        }
+        self.players
+            .iter()
+            .find(|player| player.lives == 0)
+            .map(|player| player.player)
+    }
+
+    fn points_winner(&self) -> Option<controller::Player> {
+        // the best score among the players meeting a points condition
+        let target = self
+            .win_conditions
+            .iter()
+            .filter_map(|condition| match condition {
+                gamemode::WinCondition::Points(points) => Some(*points),
+                _ => None,
+            })
+            .min()?;
+        self.players
+            .iter()
+            .filter(|player| player.score >= target)
+            .max_by_key(|player| player.score)
+            .map(|player| player.player)
+    }
+
+    fn top_scorer(&self) -> Option<controller::Player> {
+        // nobody is on top when the best score is shared
+        let best = self.players.iter().map(|player| player.score).max()?;
+        let mut top = self.players.iter().filter(|player| player.score == best);
+        match (top.next(), top.next()) {
+            (Some(player), None) => Some(player.player),
+            _ => None,
+        }
+    }
+
+    fn next_level(&mut self, events: &mut Vec<Event>) {
+        if self.level_index + 1 >= self.levels.len() {
+            // the run ends after the last level, which is only won outright by clearing blocks
+            let cleared = self
+                .win_conditions
+                .contains(&gamemode::WinCondition::ClearBlocks);
+            if !cleared {
+                self.winner = self.top_scorer();
+            }
+            let victory = cleared || self.winner.map_or(false, |winner| !self.is_ai(winner));
+            self.finish(victory, events);
+            return;
+        }
+        self.level_index += 1;
+        self.level = self.levels[self.level_index].clone();
+        self.spawn_ball();
+        self.phase = Phase::Playing;
+        events.push(Event::LevelStarted);
+    }
+
+    fn ball_collision(&mut self, events: &mut Vec<Event>) {
+        for index in 1..self.balls.len() {
+            let (others, rest) = self.balls.split_at_mut(index);
+            let ball = &mut rest[0];
+            for other in others.iter_mut() {
+                if let Some(impact) = ball.collision(other, ball.velocity * settings::TICK_DURATION)
+                {
+                    events.push(Event::BallHit);
+                    ball.bounce_off(other, impact.norm_vec);
+                }
+            }
+        }
+    }
+
+    fn collision(&mut self, events: &mut Vec<Event>) {
+        // state changes are only collected here and applied once every ball has moved
+        let mut block_damage = Vec::new();
+        let mut balls_lost = Vec::new();
+        for (ball_index, ball) in self.balls.iter_mut().enumerate() {
+            // ball going out of sight
+            if ball.shape.center().x < -settings::UNIT_SIZE // TODO: better bounds, boundary ownership for multiplayer
+                || ball.shape.center().y < -settings::UNIT_SIZE
+                || ball.shape.center().x > settings::UNIT_SIZE
+                || ball.shape.center().y > settings::UNIT_SIZE
+            {
+                balls_lost.push(BallLoss {
+                    ball_index: ball_index,
+                    side: gamemode::Side::from_position(
+                        ball.shape.center().x,
+                        ball.shape.center().y,
+                    ),
+                });
+                continue;
+            }
+
+            let mut cycling = false;
+            let mut remaining = 1.0;
+            for _ in 0..settings::MAX_BOUNCES {
+                let motion = ball.velocity * (settings::TICK_DURATION * remaining);
+                let mut earliest: Option<(sweep::Impact, Obstacle)> = None;
+                for wall in self.walls.iter() {
+                    if let Some(impact) = wall.collision(&ball, motion) {
+                        if impact.is_earlier(&earliest.map(|(impact, _)| impact)) {
+                            earliest = Some((impact, Obstacle::Wall));
+                        }
+                    }
+                }
+                for (player_index, player) in self.players.iter().enumerate() {
+                    for (bar_index, bar) in player.bars.iter().enumerate() {
+                        if let Some(impact) = bar.collision(&ball, motion) {
+                            if impact.is_earlier(&earliest.map(|(impact, _)| impact)) {
+                                earliest = Some((
+                                    impact,
+                                    Obstacle::Bar {
+                                        player_index: player_index,
+                                        bar_index: bar_index,
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                }
+                let reach = motion.magnitude() / 2.0 + ball.shape.radius();
+                let (x, y) = (
+                    ball.shape.center().x + motion.dx / 2.0,
+                    ball.shape.center().y + motion.dy / 2.0,
+                );
+                for block_index in self.level.blocks_near(x, y, reach) {
+                    let block = &self.level.blocks[block_index];
+                    if let Some(impact) = block.collision(&ball, motion) {
+                        if impact.is_earlier(&earliest.map(|(impact, _)| impact)) {
+                            earliest = Some((impact, Obstacle::Block(block_index)));
+                        }
+                    }
+                }
+
+                let (impact, obstacle) = match earliest {
+                    Some(hit) => hit,
+                    None => {
+                        ball.shape.translate(motion);
+                        break;
+                    }
+                };
+                // move up to the contact and out of any penetration
+                ball.shape.translate(motion * impact.time);
+                ball.shape
+                    .translate(impact.norm_vec * (impact.depth + PUSH_OUT_MARGIN));
+                remaining *= 1.0 - impact.time;
+                // a ball already moving away only touches, e.g. after rounding errors
+                if ball.velocity.dot(impact.norm_vec) >= 0.0 {
+                    continue;
+                }
+                ball.bounce_away(impact.norm_vec);
+
+                let progress = match obstacle {
+                    Obstacle::Wall => false,
+                    Obstacle::Bar { .. } => true,
+                    Obstacle::Block(block_index) => {
+                        self.level.blocks[block_index].block_type != block::BlockType::Immortal
+                    }
+                };
+                if progress {
+                    ball.record_progress();
+                } else if ball.record_bounce() + 1 >= self.deadlock.cycle_repeats {
+                    cycling = true;
+                }
+
+                match obstacle {
+                    Obstacle::Wall => events.push(Event::WallHit),
+                    Obstacle::Bar {
+                        player_index,
+                        bar_index,
+                    } => {
+                        let player = &self.players[player_index];
+                        player.bars[bar_index].deflect(ball, &self.english);
+                        events.push(Event::BarHit);
+                        ball.owner = Some(player.player);
+                        ball.last_bar = Some(bar_index);
+                    }
+                    Obstacle::Block(block_index) => {
+                        // a ball hits a block once per tick, even when it touches it again
+                        if !block_damage.iter().any(|damage: &BlockDamage| {
+                            damage.ball_index == ball_index && damage.block_index == block_index
+                        }) {
+                            block_damage.push(BlockDamage {
+                                ball_index: ball_index,
+                                block_index: block_index,
+                                owner: ball.owner,
+                            });
+                        }
+                    }
+                }
+            }
+
+            // deadlocked balls get a small random change of direction
+            ball.idle_ticks += 1;
+            if cycling
+                || ball.idle_ticks as f32 >= self.deadlock.timeout * settings::TICK_RATE as f32
+            {
+                let angle = self.deadlock.nudge_angle.max(f32::EPSILON) as f64;
+                let mut nudge = self.rng.gen_range(angle / 2.0, angle);
+                if self.rng.gen::<bool>() {
+                    nudge = -nudge;
+                }
+                ball.nudge(nudge);
+                events.push(Event::BallNudged);
+            }
+        }
+        self.apply_block_damage(&block_damage, events);
+        self.apply_balls_lost(&balls_lost, events);
+        if self.balls.is_empty() {
+            self.spawn_ball();
+        }
+    }
+
+    fn spawn_ball(&mut self) {
+        self.balls.push(ball::Ball::new(
+            Point::new(settings::BALL_SPAWN.0, settings::BALL_SPAWN.1),
+            self.ball_speed,
+            &mut self.rng,
+        ));
+        self.launch_ticks = launch_timeout();
+    }
+
+    fn apply_block_damage(&mut self, block_damage: &[BlockDamage], events: &mut Vec<Event>) {
+        // hits are applied in ball order, a block destroyed earlier in the tick takes no more hits
+        let mut destroyed_blocks = Vec::new();
+        let mut multiballs = Vec::new();
+        for damage in block_damage.iter() {
+            if destroyed_blocks.contains(&damage.block_index) {
+                continue;
+            }
+            self.award_block_points(damage.block_index, damage.owner);
+            let destroyed = self.level.blocks[damage.block_index].hit();
+            if destroyed {
+                destroyed_blocks.push(damage.block_index);
+                // only direct hits spawn balls, multiball blocks caught in a blast just break
+                let block = &self.level.blocks[damage.block_index];
+                if block.block_type == block::BlockType::Multiball {
+                    multiballs.push((damage.ball_index, block.shape.center()));
+                }
+                self.explode(
+                    damage.block_index,
+                    damage.owner,
+                    &mut destroyed_blocks,
+                    events,
+                );
+            }
+            events.push(Event::BlockHit {
+                destroyed: destroyed,
+            });
+        }
+        self.level.remove_blocks(&destroyed_blocks);
+        for (ball_index, block_center) in multiballs {
+            self.spawn_multiball(ball_index, block_center, events);
+        }
+    }
+
+    fn spawn_multiball(&mut self, ball_index: usize, block_center: Point, events: &mut Vec<Event>) {
+        let multiball = self.level.multiball;
+        let ball = &self.balls[ball_index];
+        let bar = match (multiball.spawn, ball.owner, ball.last_bar) {
+            (levels::MultiballSpawn::Bar, Some(owner), Some(bar_index)) => self
+                .players
+                .iter()
+                .find(|player| player.player == owner)
+                .and_then(|player| player.bars.get(bar_index)),
+            _ => None,
+        };
+        // balls leave the bar as if just deflected by it, or spread around the hitting ball
+        let (origin, heading) = match bar {
+            Some(bar) => {
+                let normal = bar.inward_normal();
+                let center = bar.shape.center();
+                let offset = settings::norm_to_unit(0.03);
+                (
+                    Point::new(center.x + normal.dx * offset, center.y + normal.dy * offset),
+                    normal,
+                )
+            }
+            None => (block_center, ball.velocity),
+        };
+        let heading_angle = heading.dy.atan2(heading.dx);
+        let mut spawned = Vec::new();
+        for index in 0..multiball.count as usize {
+            if self.balls.len() + spawned.len() >= settings::MAX_BALLS {
+                break;
+            }
+            // alternating sides of the heading, further out with every pair
+            let step = (index / 2 + 1) as f32 * MULTIBALL_SPREAD;
+            let side = if index % 2 == 0 { step } else { -step };
+            let angle = heading_angle + side.to_radians();
+            let direction = Vector {
+                dx: angle.cos(),
+                dy: angle.sin(),
+            };
+            // neighbours are MULTIBALL_SPREAD apart, far enough out to not overlap each other
+            let offset = ball.shape.radius() * 1.1 / (MULTIBALL_SPREAD / 2.0).to_radians().sin();
+            let position = Point::new(
+                origin.x + direction.dx * offset,
+                origin.y + direction.dy * offset,
+            );
+            spawned.push(ball.split(position, direction));
+        }
+        if !spawned.is_empty() {
+            events.push(Event::MultiballSpawned);
+        }
+        self.balls.extend(spawned);
+    }
+
+    fn explode(
+        &mut self,
+        block_index: usize,
+        owner: Option<controller::Player>,
+        destroyed_blocks: &mut Vec<usize>,
+        events: &mut Vec<Event>,
+    ) {
+        // explosive blocks destroyed by a blast explode as well, the ball owner gets all the points
+        let explosions = self.level.explosions;
+        let mut pending = vec![block_index];
+        while let Some(exploding) = pending.pop() {
+            let block = &self.level.blocks[exploding];
+            if block.block_type != block::BlockType::Explosive {
+                continue;
+            }
+            let center = block.shape.center();
+            events.push(Event::Explosion {
+                x: center.x,
+                y: center.y,
+                radius: (explosions.radius as f32 * 3.0f32.sqrt() + 1.0) * self.level.block_size,
+            });
+            for index in self.level.blocks_in_range(block.index, explosions.radius) {
+                if destroyed_blocks.contains(&index) {
+                    continue;
+                }
+                self.award_block_points(index, owner);
+                if self.level.blocks[index].blast(explosions.destroy_immortal) {
+                    destroyed_blocks.push(index);
+                    pending.push(index);
+                }
+            }
+        }
+    }
+
+    fn award_block_points(&mut self, block_index: usize, owner: Option<controller::Player>) {
+        let points = self
+            .points
+            .get_block_points(&self.level.blocks[block_index].block_type);
+        if let Some(owner) = owner {
+            for player in self.players.iter_mut() {
+                if player.player == owner {
+                    player.score += points;
+                }
+            }
+        }
+    }
+
+    fn apply_balls_lost(&mut self, balls_lost: &[BallLoss], events: &mut Vec<Event>) {
+        // every lost ball costs points, but lives only go with the last balls in play,
+        // one for each player who let a ball out, however many balls that were
+        let last_balls = !balls_lost.is_empty() && balls_lost.len() == self.balls.len();
+        let mut charged = Vec::new();
+        for loss in balls_lost.iter() {
+            for (player_index, player) in self.players.iter_mut().enumerate() {
+                if player.bars.iter().any(|bar| bar.side == loss.side) {
+                    player.score += self.points.ball_lost;
+                    if !charged.contains(&player_index) {
+                        charged.push(player_index);
+                    }
+                }
+            }
+            events.push(Event::BallLost {
+                side: loss.side.clone(),
+            });
+        }
+        if last_balls {
+            if self.lives.shared {
+                self.shared_lives = self.shared_lives.saturating_sub(1);
+            } else {
+                for player_index in charged {
+                    let player = &mut self.players[player_index];
+                    player.lives = player.lives.saturating_sub(1);
+                }
+            }
+        }
+        // indices were collected in ascending order
+        for loss in balls_lost.iter().rev() {
+            self.balls.remove(loss.ball_index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulation(gamemode_path: &str, difficulty: gamemode::Difficulty) -> Simulation {
+        let mode = gamemode::GameMode::new(gamemode_path, difficulty).unwrap();
+        let mut simulation = Simulation::new(mode, 0, 1);
+        simulation.launch_ticks = 0;
+        simulation
+    }
+
+    fn versus_ai() -> (Simulation, usize, usize) {
+        let simulation = simulation(
+            "config/gamemodes/versus-ai.yaml",
+            gamemode::Difficulty::Normal,
+        );
+        let human = simulation
+            .players
+            .iter()
+            .position(|player| player.ai.is_none())
+            .unwrap();
+        let ai = simulation
+            .players
+            .iter()
+            .position(|player| player.ai.is_some())
+            .unwrap();
+        (simulation, human, ai)
+    }
+
+    fn add_ball(simulation: &mut Simulation, x: f32, y: f32, velocity: Vector) -> usize {
+        let mut ball = simulation.balls[0].split(Point::new(x, y), velocity);
+        ball.velocity = velocity;
+        simulation.balls.push(ball);
+        simulation.balls.len() - 1
+    }
+
+    fn single_block_level(block_type: block::BlockType) -> levels::Level {
+        levels::Level::from_blocks(
+            String::new(),
+            String::from("Single block"),
+            levels::LevelShape::Hexagonal,
+            70.0,
+            levels::Explosions::default(),
+            levels::Multiball::default(),
+            vec![
+                block::Block::new(block::GridIndex { q: 0, r: 0 }, 70.0, block_type),
+                block::Block::new(
+                    block::GridIndex { q: 3, r: -3 },
+                    70.0,
+                    block::BlockType::Immortal,
+                ),
+            ],
+        )
+    }
+
+    fn hit_block_twice(block_type: block::BlockType) -> (Simulation, Vec<Event>, usize, usize) {
+        // two balls of different owners below the block, both reaching it during the next tick
+        let (mut simulation, human, ai) = versus_ai();
+        simulation.level = single_block_level(block_type);
+        let velocity = Vector {
+            dx: 0.0,
+            dy: -settings::norm_to_unit(0.6),
+        };
+        let y = 70.0 * 3.0f32.sqrt() / 2.0 + simulation.balls[0].shape.radius() + 2.0;
+        let first = add_ball(&mut simulation, -20.0, y, velocity);
+        let second = add_ball(&mut simulation, 20.0, y, velocity);
+        simulation.balls[first].owner = Some(simulation.players[human].player);
+        simulation.balls[second].owner = Some(simulation.players[ai].player);
+        simulation.balls.remove(0);
+        let events = simulation.step(&[]);
+        (simulation, events, human, ai)
+    }
+
+    fn block_hits(events: &[Event]) -> Vec<bool> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::BlockHit { destroyed } => Some(*destroyed),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn balls_hitting_the_same_block_damage_it_in_ball_order() {
+        let (simulation, events, human, ai) = hit_block_twice(block::BlockType::Basic2);
+        assert_eq!(block_hits(&events), vec![false, true]);
+        assert_eq!(simulation.level.blocks.len(), 1);
+        // the first ball hit a Basic2 block, the second one what was left of it
+        assert_eq!(
+            simulation.players[human].score,
+            simulation
+                .points
+                .get_block_points(&block::BlockType::Basic2)
+        );
+        assert_eq!(
+            simulation.players[ai].score,
+            simulation.points.get_block_points(&block::BlockType::Basic)
+        );
+    }
+
+    #[test]
+    fn destroyed_block_takes_no_more_hits() {
+        let (simulation, events, human, ai) = hit_block_twice(block::BlockType::Basic);
+        assert_eq!(block_hits(&events), vec![true]);
+        assert_eq!(simulation.level.blocks.len(), 1);
+        assert_eq!(
+            simulation.players[human].score,
+            simulation.points.get_block_points(&block::BlockType::Basic)
+        );
+        assert_eq!(simulation.players[ai].score, 0);
+    }
+
+    #[test]
+    fn ball_touching_a_block_hits_it_once() {
+        let (mut simulation, human, _) = versus_ai();
+        simulation.level = single_block_level(block::BlockType::Basic2);
+        let velocity = Vector {
+            dx: -2.0,
+            dy: -settings::norm_to_unit(0.6),
+        };
+        // just below the block, ending the bounce a rounding error inside its surface
+        let y = 70.0 * 3.0f32.sqrt() / 2.0 + simulation.balls[0].shape.radius() + 0.01;
+        let ball = add_ball(&mut simulation, -16.9, y, velocity);
+        simulation.balls[ball].owner = Some(simulation.players[human].player);
+        simulation.balls.remove(0);
+        let events = simulation.step(&[]);
+        assert_eq!(block_hits(&events), vec![false]);
+        assert_eq!(simulation.level.blocks.len(), 2);
+        assert!(simulation.level.blocks[0].block_type == block::BlockType::Basic);
+        assert_eq!(
+            simulation.players[human].score,
+            simulation
+                .points
+                .get_block_points(&block::BlockType::Basic2)
+        );
+        assert!(simulation.balls[0].velocity.dy > 0.0);
+    }
+
+    fn chain_reaction(destroy_immortal: bool) -> (Simulation, Vec<Event>, usize) {
+        // an explosive next to another one, each with a neighbour only it can reach
+        let (mut simulation, human, _) = versus_ai();
+        let block = |q: i32, block_type: block::BlockType| {
+            block::Block::new(block::GridIndex { q: q, r: 0 }, 70.0, block_type)
+        };
+        simulation.level = levels::Level::from_blocks(
+            String::new(),
+            String::from("Chain reaction"),
+            levels::LevelShape::Hexagonal,
+            70.0,
+            levels::Explosions {
+                radius: 1,
+                destroy_immortal: destroy_immortal,
+            },
+            levels::Multiball::default(),
+            vec![
+                block(0, block::BlockType::Explosive),
+                block(1, block::BlockType::Explosive),
+                block(-1, block::BlockType::Immortal),
+                block(2, block::BlockType::Basic2),
+                block(3, block::BlockType::Basic),
+            ],
+        );
+        let mut events = Vec::new();
+        let damage = BlockDamage {
+            ball_index: 0,
+            block_index: 0,
+            owner: Some(simulation.players[human].player),
+        };
+        simulation.apply_block_damage(&[damage], &mut events);
+        (simulation, events, human)
+    }
+
+    fn remaining_blocks(simulation: &Simulation) -> Vec<(i32, block::BlockType)> {
+        simulation
+            .level
+            .blocks
+            .iter()
+            .map(|block| (block.index.q, block.block_type.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn explosion_sets_off_neighbouring_explosives() {
+        let (simulation, events, human) = chain_reaction(false);
+        let explosions = events
+            .iter()
+            .filter(|event| match event {
+                Event::Explosion { .. } => true,
+                _ => false,
+            })
+            .count();
+        assert_eq!(explosions, 2);
+        // the second blast only reaches one cell further, a Basic2 survives it with a hit
+        assert!(
+            remaining_blocks(&simulation)
+                == vec![
+                    (-1, block::BlockType::Immortal),
+                    (2, block::BlockType::Basic),
+                    (3, block::BlockType::Basic),
+                ]
+        );
+        let points = |block_type| simulation.points.get_block_points(&block_type);
+        assert_eq!(
+            simulation.players[human].score,
+            2 * points(block::BlockType::Explosive)
+                + points(block::BlockType::Immortal)
+                + points(block::BlockType::Basic2)
+        );
+    }
+
+    #[test]
+    fn explosion_destroys_immortal_blocks_only_when_configured() {
+        let (simulation, _, _) = chain_reaction(true);
+        assert!(
+            remaining_blocks(&simulation)
+                == vec![(2, block::BlockType::Basic), (3, block::BlockType::Basic),]
+        );
+    }
+
+    #[test]
+    fn balls_lost_in_the_same_tick_are_charged_once_each() {
+        let (mut simulation, human, ai) = versus_ai();
+        let human_side = simulation.players[human].bars[0].side.clone();
+        let ai_side = simulation.players[ai].bars[0].side.clone();
+        let still = Vector { dx: 0.0, dy: 0.0 };
+        // resting where balls spawn, away from the blocks
+        let spawn = Point::new(settings::BALL_SPAWN.0, settings::BALL_SPAWN.1);
+        let kept = add_ball(&mut simulation, spawn.x, spawn.y, still);
+        add_ball(&mut simulation, spawn.x, spawn.y, still);
+        lose_ball(&mut simulation, 0, &human_side);
+        lose_ball(&mut simulation, 2, &ai_side);
+        let marker = Some(simulation.players[ai].player);
+        simulation.balls[kept].owner = marker;
+        let events = simulation.step(&[]);
+        let lost: Vec<gamemode::Side> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::BallLost { side } => Some(side.clone()),
+                _ => None,
+            })
+            .collect();
+        assert!(lost == vec![human_side.clone(), ai_side]);
+        // the ball between the lost ones is the one left
+        assert_eq!(simulation.balls.len(), 1);
+        assert!(simulation.balls[0].owner == marker);
+        assert_eq!(simulation.players[human].score, simulation.points.ball_lost);
+        assert_eq!(simulation.players[ai].score, simulation.points.ball_lost);
+        assert_eq!(simulation.players[human].lives, simulation.lives.count);
+        assert_eq!(simulation.players[ai].lives, simulation.lives.count);
+
+        // losing every ball in play at once costs a single life
+        add_ball(&mut simulation, spawn.x, spawn.y, still);
+        lose_ball(&mut simulation, 0, &human_side);
+        lose_ball(&mut simulation, 1, &human_side);
+        simulation.step(&[]);
+        assert_eq!(
+            simulation.players[human].score,
+            3 * simulation.points.ball_lost
+        );
+        assert_eq!(simulation.players[human].lives, simulation.lives.count - 1);
+        assert_eq!(simulation.players[ai].lives, simulation.lives.count);
+        // a fresh ball waits at the spawn
+        assert_eq!(simulation.balls.len(), 1);
+        assert!(simulation.launch_ticks > 0);
+    }
+
+    fn lose_ball(simulation: &mut Simulation, ball_index: usize, side: &gamemode::Side) {
+        // just outside the arena, past the middle of the side
+        let phi = side.to_ang().to_radians();
+        let distance = 1.1 * settings::UNIT_SIZE;
+        let ball = &mut simulation.balls[ball_index];
+        ball.shape
+            .move_to(Point::new(distance * phi.cos(), distance * phi.sin()));
+    }
+
+    #[test]
+    fn only_the_last_ball_costs_a_life() {
+        let (mut simulation, human, _) = versus_ai();
+        let side = simulation.players[human].bars[0].side.clone();
+        let extra = simulation.balls[0].split(Point::new(0.0, 0.0), Vector { dx: 0.0, dy: -1.0 });
+        simulation.balls.push(extra);
+        lose_ball(&mut simulation, 0, &side);
+        simulation.step(&[]);
+        assert_eq!(simulation.balls.len(), 1);
+        assert_eq!(simulation.players[human].lives, simulation.lives.count);
+        assert_eq!(simulation.players[human].score, simulation.points.ball_lost);
+
+        lose_ball(&mut simulation, 0, &side);
+        simulation.step(&[]);
+        assert_eq!(simulation.players[human].lives, simulation.lives.count - 1);
+        assert_eq!(
+            simulation.players[human].score,
+            2 * simulation.points.ball_lost
+        );
+    }
+
+    #[test]
+    fn last_balls_lost_together_cost_one_shared_life() {
+        let mut simulation = simulation(
+            "config/gamemodes/arcade-singleplayer.yaml",
+            gamemode::Difficulty::Normal,
+        );
+        let side = simulation.players[0].bars[0].side.clone();
+        let shared_lives = simulation.shared_lives;
+        let extra = simulation.balls[0].split(Point::new(0.0, 0.0), Vector { dx: 0.0, dy: -1.0 });
+        simulation.balls.push(extra);
+        lose_ball(&mut simulation, 0, &side);
+        lose_ball(&mut simulation, 1, &side);
+        simulation.step(&[]);
+        assert_eq!(simulation.shared_lives, shared_lives - 1);
+    }
+
+    #[test]
+    fn multiball_spawns_balls_apart() {
+        let (mut simulation, _, _) = versus_ai();
+        simulation.level.multiball.count = 6;
+        simulation.balls[0].velocity = Vector { dx: 0.0, dy: -1.0 };
+        let mut events = Vec::new();
+        simulation.spawn_multiball(0, Point::new(0.0, 0.0), &mut events);
+        assert_eq!(simulation.balls.len(), 7);
+        let spawned = &simulation.balls[1..];
+        for (index, first) in spawned.iter().enumerate() {
+            for second in spawned[index + 1..].iter() {
+                let (a, b) = (first.shape.center(), second.shape.center());
+                let distance = ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+                assert!(distance > 2.0 * first.shape.radius());
+            }
+        }
+    }
+
+    fn finish_last_level(simulation: &mut Simulation, clear_blocks: bool) {
+        simulation.level_index = simulation.levels.len() - 1;
+        simulation
+            .win_conditions
+            .retain(|condition| *condition != gamemode::WinCondition::ClearBlocks);
+        if clear_blocks {
+            simulation
+                .win_conditions
+                .push(gamemode::WinCondition::ClearBlocks);
+        }
+        simulation.phase = Phase::Transition { ticks_left: 1 };
+        simulation.step(&[]);
+    }
+
+    #[test]
+    fn completed_level_leads_to_the_next_one() {
+        let (mut simulation, _, _) = versus_ai();
+        simulation.phase = Phase::Transition { ticks_left: 1 };
+        let events = simulation.step(&[]);
+        assert_eq!(simulation.level_index, 1);
+        assert!(simulation.phase == Phase::Playing);
+        assert!(events.iter().any(|event| match event {
+            Event::LevelStarted => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn clearing_the_last_level_wins_with_clear_blocks() {
+        let (mut simulation, _, _) = versus_ai();
+        let last = simulation.levels.len() - 1;
+        finish_last_level(&mut simulation, true);
+        assert!(simulation.phase == Phase::Victory);
+        assert_eq!(simulation.level_index, last);
+    }
+
+    #[test]
+    fn last_level_ends_the_run_without_clear_blocks() {
+        let (mut simulation, human, ai) = versus_ai();
+        let last = simulation.levels.len() - 1;
+        simulation.players[human].score = 200;
+        simulation.players[ai].score = 100;
+        finish_last_level(&mut simulation, false);
+        assert!(simulation.phase == Phase::Victory);
+        assert!(simulation.winner == Some(simulation.players[human].player));
+        assert_eq!(simulation.level_index, last);
+
+        let (mut simulation, human, ai) = versus_ai();
+        simulation.players[human].score = 100;
+        simulation.players[ai].score = 200;
+        finish_last_level(&mut simulation, false);
+        assert!(simulation.phase == Phase::GameOver);
+        assert!(simulation.winner == Some(simulation.players[ai].player));
+    }
+
+    #[test]
+    fn ai_reaching_the_points_is_no_victory() {
+        let (mut simulation, _, ai) = versus_ai();
+        simulation.players[ai].score = 500;
+        simulation.step(&[]);
+        assert!(simulation.phase == Phase::GameOver);
+        assert!(simulation.winner == Some(simulation.players[ai].player));
+    }
+
+    #[test]
+    fn human_reaching_the_points_wins() {
+        let (mut simulation, human, _) = versus_ai();
+        simulation.players[human].score = 500;
+        simulation.step(&[]);
+        assert!(simulation.phase == Phase::Victory);
+        assert!(simulation.winner == Some(simulation.players[human].player));
+    }
+
+    #[test]
+    fn ai_out_of_lives_is_a_victory() {
+        let (mut simulation, _, ai) = versus_ai();
+        simulation.players[ai].lives = 0;
+        simulation.step(&[]);
+        assert!(simulation.phase == Phase::Victory);
+        assert!(simulation.loser == Some(simulation.players[ai].player));
+        assert!(simulation.winner.is_none());
+    }
+
+    #[test]
+    fn human_out_of_lives_is_game_over() {
+        let (mut simulation, human, _) = versus_ai();
+        simulation.players[human].lives = 0;
+        simulation.step(&[]);
+        assert!(simulation.phase == Phase::GameOver);
+        assert!(simulation.loser == Some(simulation.players[human].player));
+    }
+}
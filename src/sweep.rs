@@ -0,0 +1,251 @@
+use geometry::base::{Point, Vector};
+use geometry::shape::*;
+
+#[derive(Clone, Copy)]
+pub struct Impact {
+    // fraction of the motion travelled before the contact
+    pub time: f32,
+    pub norm_vec: Vector,
+    // how deep the ball already is inside the obstacle, zero for a regular contact
+    pub depth: f32,
+}
+
+impl Impact {
+    pub fn is_earlier(&self, other: &Option<Impact>) -> bool {
+        match other {
+            Some(other) => self.time < other.time,
+            None => true,
+        }
+    }
+}
+
+fn vector(from: Point, to: Point) -> Vector {
+    Vector {
+        dx: to.x - from.x,
+        dy: to.y - from.y,
+    }
+}
+
+fn centroid(vertices: &[Point]) -> Point {
+    let count = vertices.len() as f32;
+    Point::new(
+        vertices.iter().map(|vertex| vertex.x).sum::<f32>() / count,
+        vertices.iter().map(|vertex| vertex.y).sum::<f32>() / count,
+    )
+}
+
+fn edge_normal(a: Point, b: Point, center: Point) -> Vector {
+    let edge = vector(a, b);
+    let normal = Vector {
+        dx: edge.dy,
+        dy: -edge.dx,
+    } * (1.0 / edge.magnitude());
+    // pointing away from the polygon center, regardless of the vertex winding
+    if normal.dot(vector(center, a)) < 0.0 {
+        normal * -1.0
+    } else {
+        normal
+    }
+}
+
+fn closest_point_on_segment(point: Point, a: Point, b: Point) -> Point {
+    let edge = vector(a, b);
+    let length = edge.dot(edge);
+    if length == 0.0 {
+        return a;
+    }
+    let u = (vector(a, point).dot(edge) / length).max(0.0).min(1.0);
+    Point::new(a.x + edge.dx * u, a.y + edge.dy * u)
+}
+
+fn penetration(circle: &Circle, vertices: &[Point], center: Point) -> Option<Impact> {
+    let position = circle.center();
+    let mut inside = true;
+    let mut closest = vertices[0];
+    let mut closest_dist = f32::MAX;
+    for (index, &a) in vertices.iter().enumerate() {
+        let b = vertices[(index + 1) % vertices.len()];
+        if edge_normal(a, b, center).dot(vector(a, position)) > 0.0 {
+            inside = false;
+        }
+        let point = closest_point_on_segment(position, a, b);
+        let dist = vector(point, position).magnitude();
+        if dist < closest_dist {
+            closest = point;
+            closest_dist = dist;
+        }
+    }
+    if inside {
+        let norm_vec = if closest_dist > 0.0 {
+            vector(position, closest) * (1.0 / closest_dist)
+        } else {
+            let offset = vector(center, position);
+            offset * (1.0 / offset.magnitude())
+        };
+        return Some(Impact {
+            time: 0.0,
+            norm_vec: norm_vec,
+            depth: closest_dist + circle.radius(),
+        });
+    }
+    if closest_dist < circle.radius() {
+        return Some(Impact {
+            time: 0.0,
+            norm_vec: vector(closest, position) * (1.0 / closest_dist),
+            depth: circle.radius() - closest_dist,
+        });
+    }
+    None
+}
+
+pub fn sweep_polygon(circle: &Circle, motion: Vector, polygon: &Polygon) -> Option<Impact> {
+    let vertices = &polygon.vertices;
+    if vertices.len() < 3 {
+        return None;
+    }
+    let center = centroid(vertices);
+    let position = circle.center();
+    let radius = circle.radius();
+
+    // broad phase with the bounding circle of the polygon
+    let extent = vertices
+        .iter()
+        .map(|&vertex| vector(center, vertex).magnitude())
+        .fold(0.0, f32::max);
+    if vector(center, position).magnitude() > extent + radius + motion.magnitude() {
+        return None;
+    }
+
+    if let Some(impact) = penetration(circle, vertices, center) {
+        return Some(impact);
+    }
+
+    let mut earliest: Option<Impact> = None;
+    for (index, &a) in vertices.iter().enumerate() {
+        let b = vertices[(index + 1) % vertices.len()];
+
+        // circle against the edge, offset outwards by the radius
+        let normal = edge_normal(a, b, center);
+        let approach = motion.dot(normal);
+        if approach < 0.0 {
+            let time = (vector(a, position).dot(normal) - radius) / -approach;
+            if time >= 0.0 && time <= 1.0 {
+                let edge = vector(a, b);
+                let contact =
+                    Point::new(position.x + motion.dx * time, position.y + motion.dy * time);
+                let u = vector(a, contact).dot(edge) / edge.dot(edge);
+                let impact = Impact {
+                    time: time,
+                    norm_vec: normal,
+                    depth: 0.0,
+                };
+                if u >= 0.0 && u <= 1.0 && impact.is_earlier(&earliest) {
+                    earliest = Some(impact);
+                }
+            }
+        }
+
+        // circle against the corner
+        let offset = vector(a, position);
+        let qa = motion.dot(motion);
+        let qb = 2.0 * offset.dot(motion);
+        let qc = offset.dot(offset) - radius * radius;
+        let discriminant = qb * qb - 4.0 * qa * qc;
+        if qa > 0.0 && qb < 0.0 && discriminant >= 0.0 {
+            let time = (-qb - discriminant.sqrt()) / (2.0 * qa);
+            if time >= 0.0 && time <= 1.0 {
+                let contact =
+                    Point::new(position.x + motion.dx * time, position.y + motion.dy * time);
+                let impact = Impact {
+                    time: time,
+                    norm_vec: vector(a, contact) * (1.0 / radius),
+                    depth: 0.0,
+                };
+                if impact.is_earlier(&earliest) {
+                    earliest = Some(impact);
+                }
+            }
+        }
+    }
+    earliest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::base::{Angle, Size};
+
+    fn rectangle(w: f32, h: f32) -> Polygon {
+        Rectangle::new(Point::new(0.0, 0.0), Size::new(w, h), Angle::new(0f64)).polygon()
+    }
+
+    fn sweep(x: f32, y: f32, dx: f32, dy: f32, polygon: &Polygon) -> Option<Impact> {
+        sweep_polygon(
+            &Circle::new(Point::new(x, y), 2.0),
+            Vector { dx: dx, dy: dy },
+            polygon,
+        )
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn time_of_impact_on_an_edge() {
+        // the edge is at y = 10, touched once the center is at y = 12
+        let impact = sweep(0.0, 30.0, 0.0, -20.0, &rectangle(20.0, 20.0)).unwrap();
+        assert_close(impact.time, 0.9);
+        assert_close(impact.norm_vec.dx, 0.0);
+        assert_close(impact.norm_vec.dy, 1.0);
+        assert_close(impact.depth, 0.0);
+    }
+
+    #[test]
+    fn time_of_impact_on_a_corner() {
+        // heading straight for the corner at (10, 10), touched a radius before it
+        let impact = sweep(20.0, 20.0, -10.0, -10.0, &rectangle(20.0, 20.0)).unwrap();
+        let diagonal = 2.0f32.sqrt();
+        assert_close(impact.time, (10.0 - diagonal) / 10.0);
+        assert_close(impact.norm_vec.dx, 1.0 / diagonal);
+        assert_close(impact.norm_vec.dy, 1.0 / diagonal);
+        assert_close(impact.depth, 0.0);
+    }
+
+    #[test]
+    fn ball_starting_inside_is_pushed_out_of_the_closest_edge() {
+        let impact = sweep(3.0, 8.0, 5.0, 0.0, &rectangle(20.0, 20.0)).unwrap();
+        assert_close(impact.time, 0.0);
+        assert_close(impact.norm_vec.dx, 0.0);
+        assert_close(impact.norm_vec.dy, 1.0);
+        // two to the edge, and a radius beyond it
+        assert_close(impact.depth, 4.0);
+
+        // overlapping the edge from outside
+        let impact = sweep(3.0, 11.0, 5.0, 0.0, &rectangle(20.0, 20.0)).unwrap();
+        assert_close(impact.time, 0.0);
+        assert_close(impact.norm_vec.dy, 1.0);
+        assert_close(impact.depth, 1.0);
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel_through_a_thin_wall() {
+        // the motion of one step would carry the ball far past the wall
+        let impact = sweep(0.0, 50.0, 0.0, -100.0, &rectangle(100.0, 2.0)).unwrap();
+        assert_close(impact.time, 0.47);
+        assert_close(impact.norm_vec.dy, 1.0);
+    }
+
+    #[test]
+    fn ball_moving_away_does_not_hit() {
+        assert!(sweep(0.0, 13.0, 0.0, 20.0, &rectangle(20.0, 20.0)).is_none());
+        assert!(sweep(13.0, 13.0, 5.0, 1.0, &rectangle(20.0, 20.0)).is_none());
+        // passing by along the edge
+        assert!(sweep(-20.0, 13.0, 40.0, 0.0, &rectangle(20.0, 20.0)).is_none());
+    }
+}
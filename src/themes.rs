@@ -1,9 +1,45 @@
 use crate::component::block;
+use crate::component::controller;
+use crate::config;
+use crate::config::ConfigError;
 use ggez::*;
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
-use yaml_rust::*;
+use std::convert::TryFrom;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub graphics::Color);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        decode_color(&input)
+            .map(HexColor)
+            .ok_or_else(|| format!("Unable to decode color: {}", input))
+    }
+}
+
+#[derive(Deserialize)]
+struct ThemeFile {
+    theme: ThemeConfig,
+}
+
+#[derive(Deserialize)]
+pub struct ThemeConfig {
+    pub background: HexColor,
+    pub wall: HexColor,
+    pub player1: HexColor,
+    pub player2: HexColor,
+    pub player3: HexColor,
+    pub blocks: Vec<BlockColorConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct BlockColorConfig {
+    pub block: block::BlockType,
+    pub color: HexColor,
+}
 
 pub struct Theme {
     pub background: graphics::Color,
@@ -15,23 +51,46 @@ pub struct Theme {
 }
 
 impl Theme {
-    pub fn new(file_path: String) -> Self {
-        let mut f = File::open(file_path).expect("Unable to open theme file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("Unable to read theme file");
-        let yaml = &YamlLoader::load_from_str(&contents).unwrap()[0]["theme"];
-        Theme {
-            background: decode_color(
-                yaml["background"]
-                    .as_str()
-                    .expect("Missing property: background"),
-            ),
-            wall: decode_color(yaml["wall"].as_str().expect("Missing property: wall")),
-            player1: decode_color(yaml["player1"].as_str().expect("Missing property: player1")),
-            player2: decode_color(yaml["player2"].as_str().expect("Missing property: player2")),
-            player3: decode_color(yaml["player3"].as_str().expect("Missing property: player3")),
-            blocks: parse_blocks(yaml),
+    pub fn new(file_path: String) -> Result<Self, ConfigError> {
+        let file: ThemeFile = config::load(&file_path)?;
+        let theme = file.theme;
+        let blocks: HashMap<block::BlockType, graphics::Color> = theme
+            .blocks
+            .iter()
+            .map(|entry| (entry.block, entry.color.0))
+            .collect();
+        for block_type in [
+            block::BlockType::Basic,
+            block::BlockType::Basic2,
+            block::BlockType::Basic3,
+            block::BlockType::Immortal,
+            block::BlockType::Explosive,
+            block::BlockType::Multiball,
+        ]
+        .iter()
+        {
+            if !blocks.contains_key(block_type) {
+                return Err(ConfigError::new(
+                    &file_path,
+                    "theme.blocks",
+                    "Missing color for a block type",
+                ));
+            }
+        }
+        Ok(Theme {
+            background: theme.background.0,
+            wall: theme.wall.0,
+            player1: theme.player1.0,
+            player2: theme.player2.0,
+            player3: theme.player3.0,
+            blocks: blocks,
+        })
+    }
+    pub fn get_player_color(&self, player: &controller::Player) -> graphics::Color {
+        match player {
+            controller::Player::Player1 => self.player1,
+            controller::Player::Player2 => self.player2,
+            controller::Player::Player3 => self.player3,
         }
     }
     pub fn get_block_color(&self, block_type: &block::BlockType) -> graphics::Color {
@@ -39,38 +98,25 @@ impl Theme {
     }
 }
 
-fn decode_color(s: &str) -> graphics::Color {
+fn decode_color(s: &str) -> Option<graphics::Color> {
+    if !s.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&s[i..i + 2], 16).ok();
     if s.len() == 6 {
-        graphics::Color::from_rgb(
-            u8::from_str_radix(&s[0..2], 16).expect("Unable to decode color"),
-            u8::from_str_radix(&s[2..4], 16).expect("Unable to decode color"),
-            u8::from_str_radix(&s[4..6], 16).expect("Unable to decode color"),
-        )
+        Some(graphics::Color::from_rgb(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+        ))
     } else if s.len() == 8 {
-        graphics::Color::from_rgba(
-            u8::from_str_radix(&s[0..2], 16).expect("Unable to decode color"),
-            u8::from_str_radix(&s[2..4], 16).expect("Unable to decode color"),
-            u8::from_str_radix(&s[4..6], 16).expect("Unable to decode color"),
-            u8::from_str_radix(&s[6..8], 16).expect("Unable to decode color"),
-        )
+        Some(graphics::Color::from_rgba(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+        ))
     } else {
-        panic!("Unable to decode color")
-    }
-}
-
-fn parse_blocks(yaml: &yaml_rust::Yaml) -> HashMap<block::BlockType, graphics::Color> {
-    let mut blocks = HashMap::new();
-    if !yaml["blocks"].is_array() {
-        panic! {"Invalid property: blocks"}
-    }
-    for node in yaml["blocks"].as_vec().unwrap() {
-        let block_type = block::BlockType::from_str(
-            node["block"]
-                .as_str()
-                .expect("Missing property: block type"),
-        );
-        let block_color = decode_color(node["color"].as_str().expect("Missing property: color"));
-        blocks.insert(block_type, block_color);
+        None
     }
-    blocks
 }